@@ -0,0 +1,81 @@
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Tells the time and waits, so that time-dependent code can be driven
+/// by tests.
+pub trait Clock {
+  /// The current time.
+  fn now(&self) -> SystemTime;
+  /// Blocks for a given duration.
+  fn sleep(&self, duration: Duration);
+}
+
+/// The real clock.
+#[derive(Clone, Copy)]
+pub struct System;
+
+impl Clock for System {
+  fn now(&self) -> SystemTime {
+    SystemTime::now()
+  }
+
+  fn sleep(&self, duration: Duration) {
+    thread::sleep(duration)
+  }
+}
+
+/// A clock that only moves when told to, sleeping included.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct Fake(
+  std::rc::Rc<std::cell::Cell<SystemTime>>,
+  std::rc::Rc<std::cell::Cell<usize>>,
+);
+
+#[cfg(test)]
+impl Fake {
+  /// Starts at the given time.
+  pub fn at(time: SystemTime) -> Self {
+    Fake(
+      std::rc::Rc::new(std::cell::Cell::new(time)),
+      std::rc::Rc::new(std::cell::Cell::new(0)),
+    )
+  }
+
+  /// Moves time forward.
+  pub fn advance(&self, duration: Duration) {
+    self.0.set(self.0.get() + duration)
+  }
+
+  /// How many times sleep was called.
+  pub fn sleeps(&self) -> usize {
+    self.1.get()
+  }
+}
+
+#[cfg(test)]
+impl Clock for Fake {
+  fn now(&self) -> SystemTime {
+    self.0.get()
+  }
+
+  fn sleep(&self, duration: Duration) {
+    self.1.set(self.1.get() + 1);
+    self.advance(duration)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Clock, Fake};
+  use std::time::{Duration, UNIX_EPOCH};
+
+  #[test]
+  fn a_fake_clock_should_only_move_when_told_to() {
+    let clock = Fake::at(UNIX_EPOCH);
+    assert_eq!(UNIX_EPOCH, clock.now());
+    clock.sleep(Duration::from_secs(2));
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(UNIX_EPOCH + Duration::from_secs(3), clock.now());
+  }
+}
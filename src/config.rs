@@ -0,0 +1,100 @@
+use crate::layout::FingerprintMode;
+use crate::repository::{Format, StorageError};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the file holding defaults, in the store.
+const FILE: &str = "config.toml";
+
+/// Defaults read from the store so flags need not be given every time.
+/// Flags override them, and they override built-in defaults.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// How layouts are identified, as given with --fingerprint.
+  pub fingerprint: Option<String>,
+  /// How layouts are written to the store, as given with --store-format.
+  pub store_format: Option<String>,
+  /// Whether output commands are sent together, false being like
+  /// --one-by-one.
+  pub combine_commands: Option<bool>,
+}
+
+impl Config {
+  /// Reads the configuration of a store. A missing file leaves every
+  /// default as built in.
+  pub fn load(store: &str) -> Result<Self, StorageError> {
+    let path = Path::new(store).join(FILE);
+    let name = path.to_string_lossy().to_string();
+    let data = match fs::read_to_string(&path) {
+      Ok(data) => data,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+      Err(err) => return Err(StorageError::Io(err)),
+    };
+    let config = toml::from_str::<Config>(&data)
+      .map_err(|err| StorageError::Invalid(name.clone(), err.to_string()))?;
+    config
+      .validate()
+      .map(|()| config)
+      .map_err(|reason| StorageError::Invalid(name, reason))
+  }
+
+  /// Checks values are known, so mistakes are reported once rather than
+  /// when they are used.
+  fn validate(&self) -> Result<(), String> {
+    if let Some(ref mode) = self.fingerprint {
+      FingerprintMode::from_name(mode)
+        .ok_or_else(|| format!("unknown fingerprint mode: {}", mode))?;
+    }
+    if let Some(ref format) = self.store_format {
+      Format::from_name(format).ok_or_else(|| format!("unknown store format: {}", format))?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn with_config<T, F>(data: &str, test: F) -> T
+  where
+    F: FnOnce(&str) -> T,
+  {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join(FILE), data).unwrap();
+    test(dir.path().to_str().unwrap())
+  }
+
+  #[test]
+  fn a_missing_file_should_leave_defaults_as_built_in() {
+    let dir = tempfile::tempdir().unwrap();
+    let actual = Config::load(dir.path().to_str().unwrap()).unwrap();
+    assert_eq!(Config::default(), actual);
+  }
+
+  #[test]
+  fn defaults_should_be_read_from_the_file() {
+    let data = "fingerprint = \"model-only\"\nstore_format = \"yaml\"\ncombine_commands = false\n";
+    let actual = with_config(data, |store| Config::load(store).unwrap());
+    assert_eq!(Some(String::from("model-only")), actual.fingerprint);
+    assert_eq!(Some(String::from("yaml")), actual.store_format);
+    assert_eq!(Some(false), actual.combine_commands);
+  }
+
+  #[test]
+  fn unknown_values_should_be_reported() {
+    let actual = with_config("store_format = \"xml\"\n", Config::load);
+    assert!(actual
+      .unwrap_err()
+      .to_string()
+      .ends_with("unknown store format: xml"));
+  }
+
+  #[test]
+  fn unknown_keys_should_be_reported() {
+    assert!(with_config("fingerprints = \"strict\"\n", Config::load).is_err());
+  }
+}
@@ -0,0 +1,74 @@
+use crate::clock::Clock;
+use std::fs;
+use std::time::Duration;
+
+/// How often the sentinel file is looked for.
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tells whether the user accepted a layout being tried.
+pub trait Confirmation {
+  /// Blocks until the user confirms or the timeout elapses, returning
+  /// true only in the former case.
+  fn wait(&mut self, timeout: Duration) -> bool;
+}
+
+/// Confirmation given by creating a file, as done by a second invocation.
+pub struct Sentinel<C: Clock>(String, C);
+
+impl<C: Clock> Sentinel<C> {
+  /// Watches a given path, discarding any stale confirmation.
+  pub fn new(path: String, clock: C) -> Self {
+    let _ = fs::remove_file(&path);
+    Sentinel(path, clock)
+  }
+}
+
+impl<C: Clock> Confirmation for Sentinel<C> {
+  fn wait(&mut self, timeout: Duration) -> bool {
+    let deadline = self.1.now() + timeout;
+    loop {
+      if fs::remove_file(&self.0).is_ok() {
+        return true;
+      }
+      match deadline.duration_since(self.1.now()) {
+        Ok(left) if left > Duration::from_millis(0) => self.1.sleep(left.min(INTERVAL)),
+        _ => return false,
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Confirmation, Sentinel};
+  use crate::clock::{Clock, Fake};
+  use std::time::{Duration, UNIX_EPOCH};
+
+  #[test]
+  fn an_existing_file_should_confirm() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("confirmed").to_str().unwrap().to_string();
+    let mut sut = Sentinel::new(path.clone(), Fake::at(UNIX_EPOCH));
+    std::fs::write(&path, "true").unwrap();
+    assert!(sut.wait(Duration::from_millis(10)));
+    assert!(!std::path::Path::new(&path).exists());
+  }
+
+  #[test]
+  fn a_stale_file_should_not_confirm() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("confirmed").to_str().unwrap().to_string();
+    std::fs::write(&path, "true").unwrap();
+    assert!(!Sentinel::new(path, Fake::at(UNIX_EPOCH)).wait(Duration::from_millis(10)));
+  }
+
+  #[test]
+  fn it_should_give_up_once_the_clock_passes_the_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("confirmed").to_str().unwrap().to_string();
+    let clock = Fake::at(UNIX_EPOCH);
+    let timeout = Duration::from_secs(60 * 60);
+    assert!(!Sentinel::new(path, clock.clone()).wait(timeout));
+    assert_eq!(UNIX_EPOCH + timeout, clock.now());
+  }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+/// An input device along with the settings restored with the layout.
+/// Unset settings are left to Sway.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+  /// Sway's identifier of the device, as `vendor:product:name`.
+  pub identifier: String,
+  /// The kind of device, such as `keyboard` or `touchpad`.
+  #[serde(rename = "type")]
+  pub kind: String,
+  /// Keyboard layouts, such as `us,fr`. Sway only reports their
+  /// descriptions, so this is set by hand.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub xkb_layout: Option<String>,
+  /// Index of the active keyboard layout.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub xkb_active_layout_index: Option<u32>,
+  /// Pointer acceleration, between -1 and 1.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub accel_speed: Option<f64>,
+  /// Whether scrolling moves the content rather than the view.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub natural_scroll: Option<bool>,
+  /// Whether tapping a touchpad clicks.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub tap: Option<bool>,
+}
+
+/// An input device as reported by Sway, pointer settings being nested.
+#[derive(Deserialize)]
+struct Reported {
+  identifier: String,
+  #[serde(rename = "type")]
+  kind: String,
+  #[serde(default)]
+  xkb_active_layout_index: Option<u32>,
+  #[serde(default)]
+  libinput: Libinput,
+}
+
+/// The libinput settings Sway reports, toggles as `enabled` or
+/// `disabled`.
+#[derive(Deserialize, Default)]
+struct Libinput {
+  #[serde(default)]
+  accel_speed: Option<f64>,
+  #[serde(default)]
+  natural_scroll: Option<String>,
+  #[serde(default)]
+  tap: Option<String>,
+}
+
+impl Input {
+  /// The commands restoring each setting of the device.
+  pub fn commands(&self) -> Vec<String> {
+    let input = format!("input \"{}\"", self.identifier);
+    let toggle = |on: bool| match on {
+      true => "enabled",
+      false => "disabled",
+    };
+    vec![
+      self
+        .xkb_layout
+        .as_ref()
+        .map(|layout| format!("{} xkb_layout {}", input, layout)),
+      self
+        .xkb_active_layout_index
+        .map(|index| format!("{} xkb_switch_layout {}", input, index)),
+      self
+        .accel_speed
+        .map(|speed| format!("{} pointer_accel {}", input, speed)),
+      self
+        .natural_scroll
+        .map(|on| format!("{} natural_scroll {}", input, toggle(on))),
+      self.tap.map(|on| format!("{} tap {}", input, toggle(on))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+  }
+}
+
+impl From<Reported> for Input {
+  fn from(reported: Reported) -> Self {
+    let enabled = |toggle: Option<String>| toggle.map(|t| t == "enabled");
+    Input {
+      identifier: reported.identifier,
+      kind: reported.kind,
+      xkb_layout: None,
+      xkb_active_layout_index: reported.xkb_active_layout_index,
+      accel_speed: reported.libinput.accel_speed,
+      natural_scroll: enabled(reported.libinput.natural_scroll),
+      tap: enabled(reported.libinput.tap),
+    }
+  }
+}
+
+/// Reads the devices Sway reports, keeping those with settings worth
+/// restoring.
+pub fn parse(data: &[u8]) -> Result<Vec<Input>, serde_json::Error> {
+  serde_json::from_slice::<Vec<Reported>>(data).map(|reported| {
+    reported
+      .into_iter()
+      .map(Input::from)
+      .filter(|input| !input.commands().is_empty())
+      .collect()
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_should_read_the_settings_sway_reports() {
+    let data = br#"[
+      {"identifier": "1:1:AT_Translated_Set_2_keyboard", "type": "keyboard",
+       "xkb_active_layout_index": 1, "libinput": {"send_events": "enabled"}},
+      {"identifier": "2:7:SynPS/2_Synaptics_TouchPad", "type": "touchpad",
+       "libinput": {"accel_speed": 0.5, "natural_scroll": "enabled", "tap": "disabled"}},
+      {"identifier": "0:1:Power_Button", "type": "switch"}
+    ]"#;
+    let actual = parse(data).unwrap();
+    assert_eq!(2, actual.len());
+    assert_eq!(Some(1), actual[0].xkb_active_layout_index);
+    assert_eq!(Some(0.5), actual[1].accel_speed);
+    assert_eq!(Some(true), actual[1].natural_scroll);
+    assert_eq!(Some(false), actual[1].tap);
+  }
+
+  #[test]
+  fn it_should_restore_each_setting_that_is_set() {
+    let input = Input {
+      identifier: String::from("2:7:SynPS/2_Synaptics_TouchPad"),
+      kind: String::from("touchpad"),
+      xkb_layout: Some(String::from("us,fr")),
+      xkb_active_layout_index: None,
+      accel_speed: Some(-0.25),
+      natural_scroll: None,
+      tap: Some(true),
+    };
+    assert_eq!(
+      vec![
+        r#"input "2:7:SynPS/2_Synaptics_TouchPad" xkb_layout us,fr"#,
+        r#"input "2:7:SynPS/2_Synaptics_TouchPad" pointer_accel -0.25"#,
+        r#"input "2:7:SynPS/2_Synaptics_TouchPad" tap enabled"#,
+      ],
+      input.commands()
+    );
+  }
+
+  #[test]
+  fn unreadable_data_should_be_reported() {
+    assert!(parse(b"{").is_err());
+  }
+}
@@ -1,100 +1,508 @@
-use std::clone::Clone;
+use crate::clock::Clock;
+use crate::watch::{Events, Poll};
+use std::env;
+use std::fs;
 use std::io;
 use std::io::{Read, Write};
 use std::mem;
+use std::os::unix::fs::MetadataExt;
 use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
 
-const MAGIC_STRING: &'static str = "i3-ipc";
+pub const MAGIC_STRING: &'static str = "i3-ipc";
 
-/// The connection to Sway.
-pub struct Ipc(UnixStream);
+/// How long to wait for Sway to answer unless told otherwise.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest body accepted from the server unless told otherwise, well above
+/// what Sway sends for any realistic tree.
+pub const MAX_PAYLOAD: usize = 8 * 1024 * 1024;
+
+/// How the sockets Sway creates in the runtime directory are named, its
+/// uid and pid in between.
+const SOCKET_PREFIX: &str = "sway-ipc.";
+const SOCKET_SUFFIX: &str = ".sock";
+
+/// The connection to Sway, along with the magic string framing messages
+/// and the largest body accepted.
+pub struct Ipc(UnixStream, String, usize);
 
 impl Ipc {
-  /// Connects to a given socket path.
-  pub fn connect(path: String) -> Result<Self, io::Error> {
-    UnixStream::connect(path).map(Self)
+  /// Wraps a connected stream, setting its read timeout.
+  pub fn with_timeout(
+    stream: UnixStream,
+    magic: &str,
+    timeout: Duration,
+  ) -> Result<Self, io::Error> {
+    stream
+      .set_read_timeout(Some(timeout.max(Duration::from_millis(1))))
+      .map(|()| Ipc(stream, String::from(magic), MAX_PAYLOAD))
   }
 
+  /// Refuses responses announcing a body larger than the given size.
+  pub fn with_max_payload(self, max: usize) -> Self {
+    Ipc(self.0, self.1, max)
+  }
+}
+
+/// Exchanges raw messages with Sway.
+pub trait Transport {
   /// Sends a request and returns any valid response body as bytes.
-  pub fn roundtrip(&mut self, bytes: Vec<u8>) -> Result<Vec<u8>, io::Error> {
-    make_request(&mut self.0, bytes)
-      .and_then(|()| read_response_headers(&self.0))
-      .and_then(|size| read_n(&self.0, size))
+  fn roundtrip(&mut self, bytes: Vec<u8>) -> Result<Vec<u8>, io::Error>;
+}
+
+/// Lets a single connection be lent to each command in turn rather than
+/// cloned.
+impl<T: Transport> Transport for &mut T {
+  fn roundtrip(&mut self, bytes: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+    (**self).roundtrip(bytes)
+  }
+}
+
+impl Transport for Ipc {
+  fn roundtrip(&mut self, bytes: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+    let expected = u32_at(&bytes, mem::size_of::<u32>());
+    make_request(&mut self.0, &self.1, bytes)
+      .and_then(|()| read_response_headers(&self.0, &self.1, self.2))
+      .and_then(|(size, kind)| match kind == expected {
+        true => read_n(&self.0, size),
+        // The body is consumed anyway so that the next response is read
+        // from its start.
+        false => read_n(&self.0, size).and_then(|_| {
+          Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected a response of type {}, got {}", expected, kind),
+          ))
+        }),
+      })
+      .map_err(|err| match is_timeout(&err) {
+        true => io::Error::new(err.kind(), "no response within the read timeout"),
+        false => err,
+      })
   }
 }
 
-impl Clone for Ipc {
-  fn clone(&self) -> Self {
-    Ipc(self.0.try_clone().unwrap())
+impl Events for Ipc {
+  fn poll(&mut self, timeout: Option<Duration>) -> Poll {
+    match read_event(
+      &self.0,
+      &self.1,
+      self.2,
+      timeout.map(|t| t.max(Duration::from_millis(1))),
+    ) {
+      Ok(_) => Poll::Event,
+      Err(ref err) if is_timeout(err) => Poll::Timeout,
+      Err(_) => Poll::Closed,
+    }
   }
 }
 
+/// Connects to a socket, trying again after a delay while attempts
+/// remain, for sockets not created yet at session start. Returns the last
+/// error once every attempt failed.
+pub fn connect_with_retry(
+  path: &str,
+  attempts: u32,
+  delay: Duration,
+  clock: &impl Clock,
+) -> Result<UnixStream, io::Error> {
+  let mut attempt = 1;
+  loop {
+    match UnixStream::connect(path) {
+      Err(_) if attempt < attempts => {
+        clock.sleep(delay);
+        attempt += 1;
+      }
+      result => return result,
+    }
+  }
+}
+
+/// Connects to the given socket like `connect_with_retry`, or to the most
+/// recent Sway socket of the runtime directory when none is given. A
+/// given socket that can't be reached is an error rather than a reason to
+/// configure another instance.
+pub fn connect_or_discover(
+  path: &str,
+  attempts: u32,
+  delay: Duration,
+  clock: &impl Clock,
+) -> Result<UnixStream, io::Error> {
+  match path.is_empty() {
+    true => discover_socket().and_then(|found| connect_with_retry(&found, attempts, delay, clock)),
+    false => connect_with_retry(path, attempts, delay, clock),
+  }
+}
+
+/// Finds the socket of the running Sway in `$XDG_RUNTIME_DIR`, for
+/// environments where `SWAYSOCK` was not exported.
+pub fn discover_socket() -> Result<String, io::Error> {
+  env::var("XDG_RUNTIME_DIR")
+    .map_err(|_| {
+      io::Error::new(
+        io::ErrorKind::NotFound,
+        "$XDG_RUNTIME_DIR is unset, cannot look for a sway socket",
+      )
+    })
+    .and_then(|dir| find_socket(Path::new(&dir)))
+}
+
+/// The most recently created Sway socket of a directory, among those
+/// belonging to the owner of the directory.
+fn find_socket(dir: &Path) -> Result<String, io::Error> {
+  let owner = fs::metadata(dir)?.uid();
+  fs::read_dir(dir)?
+    .filter_map(Result::ok)
+    .filter(|entry| {
+      entry
+        .file_name()
+        .to_str()
+        .is_some_and(|n| n.starts_with(SOCKET_PREFIX) && n.ends_with(SOCKET_SUFFIX))
+    })
+    .filter_map(|entry| entry.metadata().ok().map(|m| (entry.path(), m)))
+    .filter(|(_, metadata)| metadata.uid() == owner)
+    .max_by_key(|(_, metadata)| metadata.modified().ok())
+    .and_then(|(path, _)| path.to_str().map(String::from))
+    .ok_or_else(|| {
+      io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no sway socket found in {}", dir.display()),
+      )
+    })
+}
+
+/// Sends a request through a connected pair of sockets to a thread
+/// answering with the given payload, exercising the whole framing.
+pub fn loopback(request: Vec<u8>, reply: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+  let (client, mut server) = UnixStream::pair()?;
+  let peer = thread::spawn(move || -> Result<(), io::Error> {
+    let (size, kind) = read_response_headers(&server, MAGIC_STRING, MAX_PAYLOAD)?;
+    read_n(&server, size)?;
+    let headers = [(reply.len() as u32).to_le_bytes(), kind.to_le_bytes()].concat();
+    make_request(&mut server, MAGIC_STRING, [headers, reply].concat())
+  });
+  let response = Ipc(client, String::from(MAGIC_STRING), MAX_PAYLOAD).roundtrip(request);
+  peer
+    .join()
+    .map_err(|_| io::Error::other("loopback peer panicked"))??;
+  response
+}
+
 /// Builds and writes the request to the socket.
-fn make_request(mut stream: impl Write, bytes: Vec<u8>) -> Result<(), io::Error> {
-  stream.write_all(&[MAGIC_STRING.as_bytes(), &bytes].concat())
+fn make_request(mut stream: impl Write, magic: &str, bytes: Vec<u8>) -> Result<(), io::Error> {
+  stream.write_all(&[magic.as_bytes(), &bytes].concat())
 }
 
-/// Returns the expected body length as announced by the server.
-fn read_response_headers(stream: impl Read) -> Result<usize, io::Error> {
-  let headers = read_n(stream, headers_size())?;
-  guard_against_invalid_response(&headers);
-  Ok(u32::from_le_bytes([headers[6], headers[7], headers[8], headers[9]]) as usize)
+/// Returns the expected body length and the message type as announced by
+/// the server. Fails if the headers don't start with the magic string or
+/// announce a body larger than the maximum, before anything is allocated
+/// for it.
+fn read_response_headers(
+  stream: impl Read,
+  magic: &str,
+  max: usize,
+) -> Result<(usize, u32), io::Error> {
+  match read_n(stream, headers_size(magic))? {
+    ref headers if headers.starts_with(magic.as_bytes()) => {
+      let at = magic.len();
+      match u32_at(headers, at) as usize {
+        size if size > max => Err(io::Error::new(
+          io::ErrorKind::InvalidData,
+          format!(
+            "announced body of {} bytes exceeds the limit of {}",
+            size, max
+          ),
+        )),
+        size => Ok((size, u32_at(headers, at + mem::size_of::<u32>()))),
+      }
+    }
+    _ => Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      "response does not start with the magic string",
+    )),
+  }
+}
+
+/// Reads the next event pushed by the server, waiting for it to start up
+/// to the given timeout. Once it started, the rest of the frame is given
+/// the usual read timeout, running out of which is fatal as the stream
+/// can't be resumed mid-frame. Errors if the connection was closed.
+fn read_event(
+  stream: &UnixStream,
+  magic: &str,
+  max: usize,
+  timeout: Option<Duration>,
+) -> Result<Vec<u8>, io::Error> {
+  stream.set_read_timeout(timeout)?;
+  let first = read_n(stream, 1)?;
+  stream.set_read_timeout(Some(READ_TIMEOUT))?;
+  read_response_headers(io::Cursor::new(first).chain(stream), magic, max)
+    .and_then(|(size, _)| read_n(stream, size))
+    .map_err(|err| match is_timeout(&err) {
+      true => io::Error::new(io::ErrorKind::UnexpectedEof, "event cut short"),
+      false => err,
+    })
 }
 
-/// Returns a vector with the next N bytes read from stream.
-fn read_n(stream: impl Read, n: usize) -> Result<Vec<u8>, io::Error> {
-  let mut result = Vec::<u8>::with_capacity(n);
-  stream.take(n as u64).read_to_end(&mut result)?;
+/// Decodes the little endian integer found at a given offset of the
+/// headers, such as the body length right after the magic string.
+fn u32_at(headers: &[u8], at: usize) -> u32 {
+  u32::from_le_bytes([
+    headers[at],
+    headers[at + 1],
+    headers[at + 2],
+    headers[at + 3],
+  ])
+}
+
+/// Returns true if a read gave up because of a read timeout.
+fn is_timeout(err: &io::Error) -> bool {
+  matches!(
+    err.kind(),
+    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+  )
+}
+
+/// Returns a vector with the next N bytes read from stream, however many
+/// reads it takes. Fails if the stream ends before.
+fn read_n(mut stream: impl Read, n: usize) -> Result<Vec<u8>, io::Error> {
+  let mut result = vec![0u8; n];
+  let mut filled = 0;
+  while filled < n {
+    match stream.read(&mut result[filled..]) {
+      Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+      Ok(count) => filled += count,
+      Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+      Err(err) => return Err(err),
+    }
+  }
   Ok(result)
 }
 
 /// The static headers size kept in this form to clarify its origin. The
 /// server expects every message to begin with the magic string, the
 /// message size (u32) and the message type (also u32).
-fn headers_size() -> usize {
-  MAGIC_STRING.len() + 2 * mem::size_of::<u32>()
-}
-
-/// Panics if the first six bytes of the reponse are not the magic string.
-fn guard_against_invalid_response(headers: &[u8]) {
-  assert!(headers.len() == headers_size());
-  assert_eq!(
-    String::from_utf8(headers[0..6].to_vec()).as_ref().unwrap(),
-    MAGIC_STRING
-  )
+fn headers_size(magic: &str) -> usize {
+  magic.len() + 2 * mem::size_of::<u32>()
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::clock::Fake;
+  use std::os::unix::net::UnixListener;
+  use std::time::UNIX_EPOCH;
 
   #[test]
   fn it_should_prepend_the_magic_string_to_the_message() {
     let mut c = io::Cursor::new(Vec::new());
     let expected = vec![105u8, 51u8, 45u8, 105u8, 112u8, 99u8, 0u8, 1u8, 2u8, 3u8];
-    super::make_request(&mut c, vec![0u8, 1u8, 2u8, 3u8]).unwrap();
+    super::make_request(&mut c, MAGIC_STRING, vec![0u8, 1u8, 2u8, 3u8]).unwrap();
     assert_eq!(&expected, c.get_ref());
   }
 
   #[test]
-  fn it_should_read_the_expected_payload_size_from_the_headers() {
+  fn it_should_read_the_expected_payload_size_and_type_from_the_headers() {
     let c = io::Cursor::new(vec![
       //                                     | size              | type              | payload
-      105u8, 51u8, 45u8, 105u8, 112u8, 99u8, 3u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 102u8, 111u8,
+      105u8, 51u8, 45u8, 105u8, 112u8, 99u8, 3u8, 0u8, 0u8, 0u8, 1u8, 0u8, 0u8, 0u8, 102u8, 111u8,
       111u8,
     ]);
-    let actual = super::read_response_headers(c).unwrap();
-    assert_eq!(3, actual);
+    let actual = super::read_response_headers(c, MAGIC_STRING, MAX_PAYLOAD).unwrap();
+    assert_eq!((3, 1), actual);
   }
 
   #[test]
-  #[should_panic]
-  fn it_should_panic_if_the_headers_dont_start_with_magic_string() {
+  fn it_should_fail_if_the_headers_dont_start_with_magic_string() {
     let c = io::Cursor::new(vec![
       //                               |x    | size              | type
       105u8, 51u8, 45u8, 105u8, 112u8, 98u8, 0u8, 0u8, 0u8, 0u8, 3u8, 0u8, 0u8, 0u8,
     ]);
-    super::read_response_headers(c).unwrap();
+    let actual = super::read_response_headers(c, MAGIC_STRING, MAX_PAYLOAD).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, actual.kind());
+  }
+
+  #[test]
+  fn it_should_frame_messages_with_a_custom_magic_string() {
+    let mut c = io::Cursor::new(Vec::new());
+    super::make_request(&mut c, "", vec![3u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8]).unwrap();
+    c.set_position(0);
+    assert_eq!(
+      (3, 0),
+      super::read_response_headers(c, "", MAX_PAYLOAD).unwrap()
+    );
+  }
+
+  #[test]
+  fn it_should_roundtrip_with_a_custom_magic_string() {
+    let (client, mut server) = UnixStream::pair().unwrap();
+    let peer = std::thread::spawn(move || {
+      let request = read_n(&server, 11).unwrap();
+      server.write_all(b"xyz\x02\0\0\0\x03\0\0\0ok").unwrap();
+      request
+    });
+    let actual = Ipc(client, String::from("xyz"), MAX_PAYLOAD)
+      .roundtrip(vec![0u8, 0u8, 0u8, 0u8, 3u8, 0u8, 0u8, 0u8])
+      .unwrap();
+    assert_eq!(b"ok".to_vec(), actual);
+    assert_eq!(b"xyz\0\0\0\0\x03\0\0\0".to_vec(), peer.join().unwrap());
+  }
+
+  #[test]
+  fn it_should_give_up_when_the_peer_does_not_answer() {
+    let (client, _server) = UnixStream::pair().unwrap();
+    let actual = Ipc::with_timeout(client, MAGIC_STRING, Duration::from_millis(10))
+      .unwrap()
+      .roundtrip(vec![0u8, 0u8, 0u8, 0u8, 3u8, 0u8, 0u8, 0u8])
+      .unwrap_err();
+    assert!(is_timeout(&actual));
+    assert_eq!("no response within the read timeout", actual.to_string());
+  }
+
+  #[test]
+  fn it_should_reject_a_response_of_another_type() {
+    let (client, mut server) = UnixStream::pair().unwrap();
+    let peer = std::thread::spawn(move || {
+      read_n(&server, 14).unwrap();
+      server.write_all(b"i3-ipc\x02\0\0\0\0\0\0\0[]").unwrap();
+    });
+    let actual = Ipc(client, String::from(MAGIC_STRING), MAX_PAYLOAD)
+      .roundtrip(vec![0u8, 0u8, 0u8, 0u8, 3u8, 0u8, 0u8, 0u8])
+      .unwrap_err();
+    peer.join().unwrap();
+    assert_eq!(io::ErrorKind::InvalidData, actual.kind());
+  }
+
+  #[test]
+  fn a_response_of_another_type_should_not_desync_the_connection() {
+    let (client, mut server) = UnixStream::pair().unwrap();
+    let peer = std::thread::spawn(move || {
+      read_n(&server, 14).unwrap();
+      server.write_all(b"i3-ipc\x02\0\0\0\0\0\0\0[]").unwrap();
+      read_n(&server, 14).unwrap();
+      server.write_all(b"i3-ipc\x02\0\0\0\x03\0\0\0ok").unwrap();
+    });
+    let mut ipc = Ipc(client, String::from(MAGIC_STRING), MAX_PAYLOAD);
+    let request = vec![0u8, 0u8, 0u8, 0u8, 3u8, 0u8, 0u8, 0u8];
+    assert!(ipc.roundtrip(request.clone()).is_err());
+    assert_eq!(b"ok".to_vec(), ipc.roundtrip(request).unwrap());
+    peer.join().unwrap();
+  }
+
+  #[test]
+  fn an_event_slow_to_arrive_should_be_read_whole() {
+    let (client, mut server) = UnixStream::pair().unwrap();
+    let peer = std::thread::spawn(move || {
+      server.write_all(b"i3-ipc\x02\0").unwrap();
+      std::thread::sleep(Duration::from_millis(50));
+      server.write_all(b"\0\0\x03\0\0\x80ok").unwrap();
+      server
+    });
+    let mut ipc = Ipc(client, String::from(MAGIC_STRING), MAX_PAYLOAD);
+    assert_eq!(Poll::Event, ipc.poll(Some(Duration::from_millis(10))));
+    let _server = peer.join().unwrap();
+    assert_eq!(Poll::Timeout, ipc.poll(Some(Duration::from_millis(10))));
+  }
+
+  #[test]
+  fn it_should_refuse_bodies_larger_than_the_maximum() {
+    let c = io::Cursor::new(vec![
+      //                                     | size                    | type
+      105u8, 51u8, 45u8, 105u8, 112u8, 99u8, 255u8, 255u8, 255u8, 255u8, 3u8, 0u8, 0u8, 0u8,
+    ]);
+    let actual = super::read_response_headers(c, MAGIC_STRING, MAX_PAYLOAD).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, actual.kind());
+  }
+
+  #[test]
+  fn it_should_refuse_responses_over_a_custom_maximum() {
+    let (client, mut server) = UnixStream::pair().unwrap();
+    let peer = std::thread::spawn(move || {
+      read_n(&server, 14).unwrap();
+      server.write_all(b"i3-ipc\x02\0\0\0\x03\0\0\0[]").unwrap();
+    });
+    let actual = Ipc(client, String::from(MAGIC_STRING), MAX_PAYLOAD)
+      .with_max_payload(1)
+      .roundtrip(vec![0u8, 0u8, 0u8, 0u8, 3u8, 0u8, 0u8, 0u8])
+      .unwrap_err();
+    peer.join().unwrap();
+    assert_eq!(io::ErrorKind::InvalidData, actual.kind());
+  }
+
+  #[test]
+  fn it_should_retry_until_the_socket_exists() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sway.sock");
+    let clock = Binding(path.clone(), std::cell::RefCell::new(None));
+    let actual = connect_with_retry(path.to_str().unwrap(), 3, Duration::from_secs(1), &clock);
+    assert!(actual.is_ok());
+  }
+
+  #[test]
+  fn it_should_return_the_last_error_once_attempts_are_exhausted() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing.sock");
+    let clock = Fake::at(UNIX_EPOCH);
+    let actual = connect_with_retry(path.to_str().unwrap(), 3, Duration::from_secs(1), &clock);
+    assert_eq!(io::ErrorKind::NotFound, actual.unwrap_err().kind());
+    assert_eq!(2, clock.sleeps());
+    assert_eq!(UNIX_EPOCH + Duration::from_secs(2), clock.now());
+  }
+
+  /// A clock creating the socket while the first delay elapses, as Sway
+  /// would at session start.
+  struct Binding(std::path::PathBuf, std::cell::RefCell<Option<UnixListener>>);
+
+  impl Clock for Binding {
+    fn now(&self) -> std::time::SystemTime {
+      UNIX_EPOCH
+    }
+
+    fn sleep(&self, _: Duration) {
+      self.1.replace(Some(UnixListener::bind(&self.0).unwrap()));
+    }
+  }
+
+  #[test]
+  fn a_given_socket_that_cant_be_reached_should_not_be_replaced() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing.sock");
+    let clock = Fake::at(UNIX_EPOCH);
+    let actual = connect_or_discover(path.to_str().unwrap(), 1, Duration::from_millis(0), &clock);
+    assert_eq!(io::ErrorKind::NotFound, actual.unwrap_err().kind());
+  }
+
+  #[test]
+  fn it_should_find_the_most_recent_sway_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("sway-ipc.1000.12.sock");
+    fs::write(&old, "").unwrap();
+    fs::write(dir.path().join("sway-ipc.1000.34.sock"), "").unwrap();
+    fs::write(dir.path().join("wayland-1"), "").unwrap();
+    fs::File::options()
+      .write(true)
+      .open(&old)
+      .and_then(|fd| fd.set_modified(std::time::SystemTime::now() - Duration::from_secs(60)))
+      .unwrap();
+    let actual = find_socket(dir.path()).unwrap();
+    assert!(actual.ends_with("sway-ipc.1000.34.sock"));
+  }
+
+  #[test]
+  fn it_should_fail_clearly_without_any_sway_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("wayland-1"), "").unwrap();
+    let actual = find_socket(dir.path()).unwrap_err();
+    assert_eq!(io::ErrorKind::NotFound, actual.kind());
+    assert!(actual.to_string().starts_with("no sway socket found in "));
+  }
+
+  #[test]
+  fn it_should_loop_a_request_back() {
+    let actual = super::loopback(vec![0u8, 0u8, 0u8, 0u8, 3u8, 0u8, 0u8, 0u8], b"[]".to_vec());
+    assert_eq!(b"[]".to_vec(), actual.unwrap());
   }
 
   #[test]
@@ -103,4 +511,18 @@ mod tests {
     let actual = super::read_n(c, 2).unwrap();
     assert_eq!(vec![105u8, 51u8], actual);
   }
+
+  #[test]
+  fn it_should_read_bytes_arriving_in_several_chunks() {
+    let c = io::Cursor::new(vec![105u8, 51u8]).chain(io::Cursor::new(vec![45u8, 105u8]));
+    let actual = super::read_n(c, 3).unwrap();
+    assert_eq!(vec![105u8, 51u8, 45u8], actual);
+  }
+
+  #[test]
+  fn it_should_fail_if_the_stream_ends_early() {
+    let c = io::Cursor::new(vec![105u8, 51u8]);
+    let actual = super::read_n(c, 3).unwrap_err();
+    assert_eq!(io::ErrorKind::UnexpectedEof, actual.kind());
+  }
 }
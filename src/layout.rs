@@ -1,40 +1,693 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sha2::{Digest, Sha256};
 use std::clone::Clone;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
+/// Fields that can be reset when sway reports values we can't parse.
+const OPTIONAL_FIELDS: &[&str] = &["transform"];
+
+/// Modes Sway scales backgrounds with, telling the image path apart from
+/// the arguments following it.
+const BACKGROUND_MODES: &[&str] = &["stretch", "fill", "fit", "center", "tile", "solid_color"];
+
+/// Largest scale accepted from saved layouts.
+const MAX_SCALE: f32 = 10.0;
+
+/// Pixels represented by a single character of the map.
+const MAP_SCALE: (u32, u32) = (80, 160);
+
+/// Fixed positions keyed by connector name or OEM identifier.
+pub type Pins = HashMap<String, Rect>;
+
+/// Scale factors applied to outputs wider than a given number of pixels.
+pub type Scaling = Vec<ScaleThreshold>;
+
+/// Scale given to outputs wider than a threshold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScaleThreshold {
+  pub wider_than: u32,
+  pub scale: f32,
+}
+
+/// An image drawn behind an output's windows, as saved by versions that
+/// spawned swaybg themselves. Sway draws it now, like a background.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Wallpaper {
+  /// Path to the image.
+  pub image: String,
+  /// How the image is scaled, as understood by swaybg.
+  #[serde(default = "default_mode")]
+  pub mode: String,
+}
+
+/// Matches the default of swaybg.
+fn default_mode() -> String {
+  String::from("fill")
+}
+
+/// Partial settings keyed by connector name or OEM identifier, stacked
+/// over a layout to compose it out of several files.
+pub type Fragment = HashMap<String, Overrides>;
+
+/// Settings of an output, each left as is when unset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Overrides {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub rect: Option<Rect>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub transform: Option<Transform>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub active: Option<bool>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub scale: Option<f32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub power: Option<bool>,
+}
+
+/// The rotations and flips Sway accepts, written the way it expects them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Transform {
+  Normal,
+  Rot90,
+  Rot180,
+  Rot270,
+  Flipped,
+  Flipped90,
+  Flipped180,
+  Flipped270,
+}
+
+impl Transform {
+  /// Parses a transform, accepting any quarter turn in degrees such as
+  /// `-90` for `270`.
+  pub fn parse(value: &str) -> Result<Self, String> {
+    match normalize_transform(value)?.as_str() {
+      "normal" => Ok(Transform::Normal),
+      "90" => Ok(Transform::Rot90),
+      "180" => Ok(Transform::Rot180),
+      "270" => Ok(Transform::Rot270),
+      "flipped" => Ok(Transform::Flipped),
+      "flipped-90" => Ok(Transform::Flipped90),
+      "flipped-180" => Ok(Transform::Flipped180),
+      _ => Ok(Transform::Flipped270),
+    }
+  }
+
+  /// The value Sway expects.
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Transform::Normal => "normal",
+      Transform::Rot90 => "90",
+      Transform::Rot180 => "180",
+      Transform::Rot270 => "270",
+      Transform::Flipped => "flipped",
+      Transform::Flipped90 => "flipped-90",
+      Transform::Flipped180 => "flipped-180",
+      Transform::Flipped270 => "flipped-270",
+    }
+  }
+}
+
+impl TryFrom<String> for Transform {
+  type Error = String;
+
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    Transform::parse(&value)
+  }
+}
+
+impl From<Transform> for String {
+  fn from(transform: Transform) -> Self {
+    String::from(transform.as_str())
+  }
+}
+
+impl Display for Transform {
+  fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+    f.write_str(self.as_str())
+  }
+}
+
+/// Which settings of a saved layout take over the live ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergePolicy {
+  /// Everything comes from the saved layout.
+  #[default]
+  Override,
+  /// Outputs stay where they are.
+  KeepPositions,
+  /// Outputs keep their current resolution.
+  KeepModes,
+}
+
+/// What tells monitors apart when fingerprinting a layout.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FingerprintMode {
+  /// Make, model and serial number.
+  #[default]
+  Strict,
+  /// Make and model only, for monitors reporting unstable serials.
+  ModelOnly,
+}
+
+impl FingerprintMode {
+  /// Returns the mode with the given name, if any.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "strict" => Some(FingerprintMode::Strict),
+      "model-only" => Some(FingerprintMode::ModelOnly),
+      _ => None,
+    }
+  }
+}
+
 /// The currently available outputs.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Layouts are parsed from the outputs Sway reports:
+///
+/// ```
+/// let json = r#"[{"name": "eDP1", "make": "Samsung", "model": "XYZ",
+///   "serial": "12345", "transform": "normal", "active": true,
+///   "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080}}]"#;
+/// let layout: autosway::Layout = serde_json::from_str(json).unwrap();
+/// assert_eq!(
+///   vec!["output eDP1 enable res 1920x1080 pos 0 0 transform normal"],
+///   layout.serialize_commands()
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Layout(Vec<Output>);
 
 impl Layout {
+  /// Builds a best-effort layout out of outputs that failed to parse
+  /// strictly: offending optional fields are reset and outputs still
+  /// unusable are skipped. Also returns what had to be dropped.
+  pub fn lenient(value: Value) -> Result<(Self, Vec<String>), serde_json::Error> {
+    let mut dropped = Vec::new();
+    let outputs = serde_json::from_value::<Vec<Value>>(value)?
+      .drain(..)
+      .filter_map(|o| lenient_output(o, &mut dropped))
+      .collect();
+    Ok((Layout(outputs), dropped))
+  }
+
   /// Returns afinger print that is unique for a given layout.
   pub fn fingerprint(&self) -> String {
+    self.fingerprint_with(FingerprintMode::Strict)
+  }
+
+  /// Same as fingerprint, identifying outputs as the mode says.
+  pub fn fingerprint_with(&self, mode: FingerprintMode) -> String {
     let mut hasher = Sha256::new();
-    hasher.input(self.serialize_ids().join("+++").as_bytes());
+    hasher.input(self.ids_with(mode).join("+++").as_bytes());
     format!("{:x}", hasher.result())
   }
 
-  /// A vector containing Sway commands.
-  pub fn serialize_commands(&self) -> Vec<String> {
+  /// The outputs, in the order they were reported.
+  pub fn outputs(&self) -> &[Output] {
+    &self.0
+  }
+
+  /// Number of outputs, active or not.
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+
+  /// True when no output is reported at all.
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  /// Ensures the resolution of every active output is neither empty nor
+  /// larger than the maximum, and its scale positive and below
+  /// MAX_SCALE, describing the first offending output otherwise.
+  /// Disabled outputs are left out, Sway reporting them as 0x0.
+  pub fn check_resolutions(&self, max: u32) -> Result<(), String> {
+    let out_of_bounds = |size: u32| size == 0 || size > max;
+    for o in self.0.iter().filter(|o| o.active) {
+      if out_of_bounds(o.rect.width) || out_of_bounds(o.rect.height) {
+        return Err(format!(
+          "{}: resolution {}x{} out of bounds",
+          o.name, o.rect.width, o.rect.height
+        ));
+      }
+      match o.scale {
+        Some(scale) if !(scale > 0.0 && scale <= MAX_SCALE) => {
+          return Err(format!("{}: scale {} out of bounds", o.name, scale))
+        }
+        _ => (),
+      }
+    }
+    Ok(())
+  }
+
+  /// Enables the output holding focus according to a tree as Sway
+  /// reports it, leaving the layout as is if none does.
+  pub fn activate_focused(mut self, tree: &Value) -> Self {
+    if let Some(name) = focused_output(tree) {
+      for o in self.0.iter_mut().filter(|o| o.name == name) {
+        o.active = true;
+      }
+    }
     self
-      .activate_only_output()
+  }
+
+  /// Ensures active outputs neither overlap nor leave one stranded away
+  /// from the others, and only mirror enabled outputs, describing the
+  /// first problem found. Outputs without a mode are left to Sway.
+  pub fn validate(&self) -> Result<(), String> {
+    for o in self.0.iter().filter(|o| o.active) {
+      if let Some(ref target) = o.mirror_of {
+        match self.0.iter().find(|t| &t.name == target) {
+          Some(t) if t.active => (),
+          Some(_) => return Err(format!("{} mirrors disabled output {}", o.name, target)),
+          None => return Err(format!("{} mirrors unknown output {}", o.name, target)),
+        }
+      }
+    }
+    let active: Vec<(&Output, Rect)> = self
+      .0
+      .iter()
+      .zip(self.logical_rects())
+      .filter(|(o, _)| o.active && o.mirror_of.is_none())
+      .filter(|(_, r)| r.width > 0 && r.height > 0)
+      .collect();
+    for (i, (a, ra)) in active.iter().enumerate() {
+      if let Some((b, _)) = active[i + 1..].iter().find(|(_, rb)| ra.overlaps(rb)) {
+        return Err(format!("{} overlaps {}", a.name, b.name));
+      }
+    }
+    if active.is_empty() {
+      return Ok(());
+    }
+    let mut reached = vec![false; active.len()];
+    let mut pending = vec![0];
+    while let Some(i) = pending.pop() {
+      if reached[i] {
+        continue;
+      }
+      reached[i] = true;
+      pending.extend((0..active.len()).filter(|&j| active[i].1.touches(&active[j].1)));
+    }
+    match active.iter().zip(reached).find(|(_, r)| !r) {
+      Some(((o, _), _)) => Err(format!("{} does not touch any other output", o.name)),
+      None => Ok(()),
+    }
+  }
+
+  /// Rotates a single output, identified by its connector name.
+  pub fn set_transform(mut self, name: &str, transform: &str) -> Result<Self, String> {
+    let transform = Transform::parse(transform)?;
+    match self.0.iter_mut().find(|o| o.name == name) {
+      Some(o) => o.transform = Some(transform),
+      None => return Err(format!("unknown output: {}", name)),
+    }
+    Ok(self)
+  }
+
+  /// Enables or disables a single output, identified by its connector
+  /// name.
+  pub fn set_active(mut self, name: &str, active: bool) -> Result<Self, String> {
+    match self.0.iter_mut().find(|o| o.name == name) {
+      Some(o) => o.active = active,
+      None => return Err(format!("unknown output: {}", name)),
+    }
+    Ok(self)
+  }
+
+  /// The Sway command configuring a single output, if it exists.
+  pub fn command_for(&self, name: &str) -> Option<String> {
+    self
+      .0
       .iter()
+      .find(|o| o.name == name)
       .map(sway_output_command)
+  }
+
+  /// Commands for the outputs of the target that self configures
+  /// differently, in the order serialize_commands sends them. Sway
+  /// doesn't report backgrounds, so they follow their output whenever
+  /// it changes.
+  pub fn delta(&self, target: &Layout) -> Vec<String> {
+    let current: HashSet<String> = self
+      .placed()
+      .iter()
+      .map(|(o, at)| output_command_at(o, at))
+      .collect();
+    target
+      .ordered()
+      .iter()
+      .map(|(o, at)| (o, output_command_at(o, at)))
+      .filter(|(_, command)| !current.contains(command))
+      .flat_map(|(o, command)| std::iter::once(command).chain(background_command(o)))
       .collect()
   }
 
+  /// A vector containing Sway commands, each enabled output followed by
+  /// its background if it has one. Outputs are disabled first so that
+  /// the others can be positioned where they were. Mirrors are placed
+  /// over the output they mirror.
+  pub fn serialize_commands(&self) -> Vec<String> {
+    self
+      .ordered()
+      .iter()
+      .flat_map(|(o, at)| std::iter::once(output_command_at(o, at)).chain(background_command(o)))
+      .collect()
+  }
+
+  /// Shell variable assignments describing each output, indexed in the
+  /// order outputs were reported.
+  pub fn to_env(&self) -> String {
+    self
+      .activate_only_output()
+      .iter()
+      .enumerate()
+      .flat_map(|(i, o)| {
+        vec![
+          format!("AUTOSWAY_OUTPUT_{}_NAME={}", i, o.name),
+          format!(
+            "AUTOSWAY_OUTPUT_{}_RES={}x{}",
+            i, o.rect.width, o.rect.height
+          ),
+          format!("AUTOSWAY_OUTPUT_{}_POS={},{}", i, o.rect.x, o.rect.y),
+          format!(
+            "AUTOSWAY_OUTPUT_{}_TRANSFORM={}",
+            i,
+            o.transform.map_or("normal", Transform::as_str)
+          ),
+          format!("AUTOSWAY_OUTPUT_{}_ACTIVE={}", i, o.active),
+        ]
+      })
+      .collect::<Vec<String>>()
+      .join("\n")
+  }
+
   /// Apply screen configuration of the given layout to the current
-  /// layout. Panics if we can't find an ouput with the same OEM
+  /// layout. Outputs missing from either side are left alone, so that
+  /// partial matches work. Fails if no output has the same OEM
   /// identifier.
-  pub fn merge(mut self, other: Self) -> Self {
-    for ref mut o in &mut (self.0) {
-      o.merge(
-        other
-          .find_by_id(unique_oem_identifier(&o))
-          .expect("merge: incompatible layouts"),
-      );
+  ///
+  /// ```
+  /// let output = r#"{"name": "eDP1", "make": "Samsung", "model": "XYZ",
+  ///   "serial": "12345", "transform": "normal", "active": true,
+  ///   "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080}}"#;
+  /// let live: autosway::Layout = serde_json::from_str(&format!("[{}]", output)).unwrap();
+  /// let saved: autosway::Layout =
+  ///   serde_json::from_str(&format!("[{}]", output.replace("normal", "90"))).unwrap();
+  /// let merged = live.merge(saved).unwrap();
+  /// assert_eq!(
+  ///   Some(autosway::Transform::Rot90),
+  ///   merged.outputs()[0].transform()
+  /// );
+  /// ```
+  pub fn merge(self, other: Self) -> Result<Self, MergeError> {
+    self.merge_with(other, MergePolicy::Override, FingerprintMode::Strict)
+  }
+
+  /// Same as merge, keeping some live settings depending on the policy.
+  /// Outputs are matched as the fingerprint mode identifies them.
+  pub fn merge_with(
+    mut self,
+    other: Self,
+    policy: MergePolicy,
+    mode: FingerprintMode,
+  ) -> Result<Self, MergeError> {
+    let matches = self.match_outputs(&other, mode);
+    let mut unmatched = None;
+    let mut matched = 0;
+    for (o, found) in self.0.iter_mut().zip(matches) {
+      let (live, refresh) = (o.rect.clone(), o.refresh);
+      let saved = match found {
+        Some(i) => &other.0[i],
+        None => {
+          unmatched.get_or_insert_with(|| unique_oem_identifier(o));
+          continue;
+        }
+      };
+      matched += 1;
+      o.merge(saved);
+      match policy {
+        MergePolicy::Override => (),
+        MergePolicy::KeepPositions => {
+          o.rect.x = live.x;
+          o.rect.y = live.y;
+        }
+        MergePolicy::KeepModes => {
+          o.rect.width = live.width;
+          o.rect.height = live.height;
+          o.refresh = refresh;
+        }
+      }
+    }
+    match (matched, unmatched) {
+      (0, Some(id)) => Err(MergeError(id)),
+      _ => Ok(self),
+    }
+  }
+
+  /// Forces pinned outputs to their fixed rect, whatever the saved or
+  /// merged data said. Connector names take precedence over OEM
+  /// identifiers.
+  pub fn pin(mut self, pins: &Pins) -> Self {
+    for o in &mut self.0 {
+      let fixed = pins
+        .get(&o.name)
+        .or_else(|| pins.get(&unique_oem_identifier(o)));
+      if let Some(rect) = fixed {
+        o.rect = rect.clone();
+      }
+    }
+    self
+  }
+
+  /// Overrides the settings a fragment sets, field by field. Connector
+  /// names take precedence over OEM identifiers.
+  pub fn overlay(mut self, fragment: &Fragment) -> Self {
+    for o in &mut self.0 {
+      let keys = [unique_oem_identifier(o), o.name.clone()];
+      for overrides in keys.iter().filter_map(|key| fragment.get(key)) {
+        o.overlay(overrides);
+      }
+    }
+    self
+  }
+
+  /// Lists, output by output, the fields that would change to reach the
+  /// target layout. Outputs missing from the target are ignored.
+  pub fn diff(&self, target: &Layout) -> Vec<OutputDiff> {
+    self
+      .0
+      .iter()
+      .filter_map(|o| {
+        target
+          .find_by_id(unique_oem_identifier(o))
+          .map(|t| o.diff(t))
+      })
+      .filter(|d| !d.is_empty())
+      .collect()
+  }
+
+  /// Approximates what Sway would do without configuration: every
+  /// output enabled at its current resolution, untransformed, tiled left
+  /// to right in the order they were reported.
+  pub fn defaults(&self) -> Self {
+    let mut x = 0;
+    let mut result = self.clone();
+    for o in &mut result.0 {
+      o.active = true;
+      o.transform = Some(Transform::Normal);
+      o.rect.x = x;
+      o.rect.y = 0;
+      x += o.rect.width as i32;
+    }
+    result
+  }
+
+  /// Drops outputs reported twice under the same connector name,
+  /// keeping the active one, or the first one if neither is.
+  pub fn dedup_names(self) -> Self {
+    let mut kept: Vec<Output> = Vec::with_capacity(self.0.len());
+    for o in self.0 {
+      match kept.iter().position(|k| k.name == o.name) {
+        Some(i) if o.active && !kept[i].active => kept[i] = o,
+        Some(_) => (),
+        None => kept.push(o),
+      }
+    }
+    Layout(kept)
+  }
+
+  /// Orders saved layouts from best to worst match: the exact
+  /// fingerprint first, then by number of shared outputs.
+  pub fn rank(&self, saved: Vec<(String, Layout)>) -> Vec<(String, Score)> {
+    let fingerprint = self.fingerprint();
+    let mut ranking: Vec<(String, Score)> = saved
+      .into_iter()
+      .map(|(id, l)| match l.fingerprint() == fingerprint {
+        true => (id, Score::Exact),
+        false => (id, Score::Partial(self.shared_ids(&l))),
+      })
+      .collect();
+    ranking.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranking
+  }
+
+  /// Connector name of each output, by OEM identifier.
+  pub fn connectors(&self) -> HashMap<String, String> {
+    self
+      .0
+      .iter()
+      .map(|o| (unique_oem_identifier(o), o.name.clone()))
+      .collect()
+  }
+
+  /// OEM identifiers of the outputs the other layout lacks.
+  pub fn missing_from(&self, other: &Self) -> Vec<String> {
+    let theirs: HashSet<String> = other.serialize_ids().into_iter().collect();
+    self
+      .serialize_ids()
+      .into_iter()
+      .filter(|id| !theirs.contains(id))
+      .collect()
+  }
+
+  /// Counts the outputs both layouts have in common.
+  fn shared_ids(&self, other: &Self) -> usize {
+    let mine: HashSet<String> = self.serialize_ids().into_iter().collect();
+    let theirs: HashSet<String> = other.serialize_ids().into_iter().collect();
+    mine.intersection(&theirs).count()
+  }
+
+  /// Disables outputs whose activation rule names another connected
+  /// output.
+  pub fn apply_rules(mut self) -> Self {
+    let connected: HashSet<String> = self.serialize_ids().into_iter().collect();
+    for o in &mut self.0 {
+      if let Some(ref id) = o.disabled_when {
+        o.active = o.active && !connected.contains(id);
+      }
+    }
+    self
+  }
+
+  /// Scales each active output according to the highest threshold its
+  /// width exceeds, leaving it untouched if none is.
+  pub fn apply_scaling(mut self, scaling: &[ScaleThreshold]) -> Self {
+    for o in self.0.iter_mut().filter(|o| o.active) {
+      if let Some(t) = scaling
+        .iter()
+        .filter(|t| o.rect.width > t.wider_than)
+        .max_by_key(|t| t.wider_than)
+      {
+        o.scale = Some(t.scale);
+      }
+    }
+    self
+  }
+
+  /// The outputs in JSON, indented or on a single line.
+  pub fn to_json(&self, pretty: bool) -> Result<String, serde_json::Error> {
+    match pretty {
+      true => serde_json::to_string_pretty(self),
+      false => serde_json::to_string(self),
+    }
+  }
+
+  /// Draws active outputs as labeled boxes, scaled down so that their
+  /// relative positions fit in a terminal.
+  pub fn to_map(&self) -> String {
+    let active = || self.0.iter().filter(|o| o.active);
+    let origin = (
+      active().map(|o| o.rect.x).min().unwrap_or(0),
+      active().map(|o| o.rect.y).min().unwrap_or(0),
+    );
+    let boxes: Vec<(&str, usize, usize, usize, usize)> = active()
+      .map(|o| {
+        let (x, y) = ((o.rect.x - origin.0) as u32, (o.rect.y - origin.1) as u32);
+        let left = (x / MAP_SCALE.0) as usize;
+        let top = (y / MAP_SCALE.1) as usize;
+        let right = ((x + o.rect.width) / MAP_SCALE.0) as usize;
+        let bottom = ((y + o.rect.height) / MAP_SCALE.1) as usize;
+        (
+          o.name.as_str(),
+          left,
+          top,
+          right.max(left + 3) - 1,
+          bottom.max(top + 3) - 1,
+        )
+      })
+      .collect();
+    let width = boxes.iter().map(|b| b.3 + 1).max().unwrap_or(0);
+    let height = boxes.iter().map(|b| b.4 + 1).max().unwrap_or(0);
+    let mut grid = vec![vec![' '; width]; height];
+    for &(name, left, top, right, bottom) in &boxes {
+      for &y in &[top, bottom] {
+        grid[y][left..=right].iter_mut().for_each(|c| *c = '-');
+      }
+      for row in grid.iter_mut().take(bottom + 1).skip(top) {
+        row[left] = '|';
+        row[right] = '|';
+      }
+      for &(x, y) in &[(left, top), (right, top), (left, bottom), (right, bottom)] {
+        grid[y][x] = '+';
+      }
+      for (i, c) in name.chars().take(right - left - 1).enumerate() {
+        grid[top + 1][left + 1 + i] = c;
+      }
+    }
+    grid
+      .iter()
+      .map(|row| row.iter().collect::<String>().trim_end().to_string())
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  /// Moves every output so that the one with the given OEM identifier
+  /// sits at 0,0, keeping the others where they are relative to it.
+  pub fn recenter(mut self, origin: &str) -> Result<Self, String> {
+    let (x, y) = match self.find_by_id(String::from(origin)) {
+      Some(o) => (o.rect.x, o.rect.y),
+      None => return Err(format!("unknown origin output: {}", origin)),
+    };
+    for o in self.0.iter_mut() {
+      o.rect.x -= x;
+      o.rect.y -= y;
+    }
+    Ok(self)
+  }
+
+  /// Recomputes horizontal positions from the actual widths: active
+  /// outputs sharing the same x form a column, and each column is moved
+  /// right next to the widest output of the previous one.
+  pub fn reanchor(mut self) -> Self {
+    let mut columns: Vec<i32> = self
+      .0
+      .iter()
+      .filter(|o| o.active)
+      .map(|o| o.rect.x)
+      .collect();
+    columns.sort();
+    columns.dedup();
+    let mut shifts: HashMap<i32, i32> = HashMap::with_capacity(columns.len());
+    let mut next = columns.first().cloned().unwrap_or(0);
+    for x in columns {
+      shifts.insert(x, next);
+      next += self
+        .0
+        .iter()
+        .filter(|o| o.active && o.rect.x == x)
+        .map(|o| o.rect.width as i32)
+        .max()
+        .unwrap_or(0);
+    }
+    for o in self.0.iter_mut().filter(|o| o.active) {
+      o.rect.x = shifts[&o.rect.x];
     }
     self
   }
@@ -44,13 +697,124 @@ impl Layout {
     self.0.iter().find(|o| unique_oem_identifier(&o) == id)
   }
 
+  /// The index of the saved output each output takes its settings from.
+  /// OEM identifiers match first. In model-only mode, the others then
+  /// match an output of the same make and model, preferably plugged into
+  /// the same connector. No saved output is matched twice.
+  fn match_outputs(&self, saved: &Self, mode: FingerprintMode) -> Vec<Option<usize>> {
+    let mut taken = vec![false; saved.0.len()];
+    let mut matches = Vec::with_capacity(self.0.len());
+    for o in &self.0 {
+      let id = unique_oem_identifier(o);
+      let found =
+        (0..saved.0.len()).find(|&i| !taken[i] && unique_oem_identifier(&saved.0[i]) == id);
+      if let Some(i) = found {
+        taken[i] = true;
+      }
+      matches.push(found);
+    }
+    if mode == FingerprintMode::Strict {
+      return matches;
+    }
+    for (o, found) in self.0.iter().zip(matches.iter_mut()) {
+      if found.is_some() {
+        continue;
+      }
+      let id = model_identifier(o);
+      let candidates: Vec<usize> = (0..saved.0.len())
+        .filter(|&i| !taken[i] && model_identifier(&saved.0[i]) == id)
+        .collect();
+      *found = candidates
+        .iter()
+        .find(|&&i| saved.0[i].name == o.name)
+        .or_else(|| candidates.first())
+        .copied();
+      if let Some(i) = *found {
+        taken[i] = true;
+      }
+    }
+    matches
+  }
+
   /// A sorted vector with an unique string for each output.
   fn serialize_ids(&self) -> Vec<String> {
-    let mut ids: Vec<String> = self.0.iter().map(unique_oem_identifier).collect();
+    self.ids_with(FingerprintMode::Strict)
+  }
+
+  /// Same as serialize_ids, identifying outputs as the mode says.
+  fn ids_with(&self, mode: FingerprintMode) -> Vec<String> {
+    let identify = match mode {
+      FingerprintMode::Strict => unique_oem_identifier,
+      FingerprintMode::ModelOnly => model_identifier,
+    };
+    let mut ids: Vec<String> = self.0.iter().map(identify).collect();
     ids.sort();
     ids
   }
 
+  /// The outputs, those mirroring another taking its position, mode and
+  /// transform. Mirrors of unknown outputs are left as is.
+  fn mirrored(&self) -> Vec<Output> {
+    let mut result = self.0.clone();
+    for o in result.iter_mut() {
+      let target = o
+        .mirror_of
+        .as_ref()
+        .and_then(|name| self.0.iter().find(|t| &t.name == name));
+      if let Some(t) = target {
+        o.rect = t.rect.clone();
+        o.refresh = t.refresh;
+        o.transform = t.transform;
+        o.physical = t.physical;
+        o.scale = t.scale;
+      }
+    }
+    result
+  }
+
+  /// The logical rect of each output. Outputs measured in physical pixels
+  /// right of or below another one are moved next to its logical edge, so
+  /// that neighbours with different scales keep touching.
+  fn logical_rects(&self) -> Vec<Rect> {
+    let mut rects: Vec<Rect> = self.0.iter().map(Output::logical_rect).collect();
+    let mut order: Vec<usize> = (0..self.0.len()).filter(|&i| self.0[i].active).collect();
+    order.sort_by_key(|&i| self.0[i].rect.x);
+    for (k, &i) in order.iter().enumerate() {
+      let edge = order[..k]
+        .iter()
+        .find(|&&j| self.0[j].rect.right() == self.0[i].rect.x);
+      if let (Some(&j), true) = (edge, self.0[i].is_physical()) {
+        rects[i].x = rects[j].right();
+      }
+    }
+    order.sort_by_key(|&i| self.0[i].rect.y);
+    for (k, &i) in order.iter().enumerate() {
+      let edge = order[..k]
+        .iter()
+        .find(|&&j| self.0[j].rect.bottom() == self.0[i].rect.y);
+      if let (Some(&j), true) = (edge, self.0[i].is_physical()) {
+        rects[i].y = rects[j].bottom();
+      }
+    }
+    rects
+  }
+
+  /// The outputs with mirrors resolved, each along with the logical rect
+  /// it is positioned at.
+  fn placed(&self) -> Vec<(Output, Rect)> {
+    let outputs = Layout(self.mirrored());
+    let rects = outputs.logical_rects();
+    outputs.0.into_iter().zip(rects).collect()
+  }
+
+  /// Placed outputs in the order they are sent to Sway, disabled ones
+  /// first and any single output activated.
+  fn ordered(&self) -> Vec<(Output, Rect)> {
+    let mut outputs = Layout(self.activate_only_output()).placed();
+    outputs.sort_by_key(|(o, _)| o.active);
+    outputs
+  }
+
   /// Activates any single output. Does not mutate self but instead
   /// clone and modify outputs into a new vector.
   fn activate_only_output(&self) -> Vec<Output> {
@@ -65,6 +829,18 @@ impl Layout {
   }
 }
 
+/// The saved layout lacks an output, given by OEM identifier.
+#[derive(Debug, PartialEq)]
+pub struct MergeError(pub String);
+
+impl Display for MergeError {
+  fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+    write!(f, "saved layout has no output matching {}", self.0)
+  }
+}
+
+impl std::error::Error for MergeError {}
+
 impl Display for Layout {
   /// Renders each output's string template separated by a line feed.
   fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
@@ -72,19 +848,160 @@ impl Display for Layout {
   }
 }
 
+/// How well a saved layout matches the current one.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Score {
+  /// Some outputs are shared.
+  Partial(usize),
+  /// Same set of outputs.
+  Exact,
+}
+
+impl Display for Score {
+  fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+    match self {
+      Score::Exact => write!(f, "exact"),
+      Score::Partial(n) => write!(f, "{} shared", n),
+    }
+  }
+}
+
 /// Represents an output.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Output {
   name: String,
   make: String,
   model: String,
   serial: String,
-  transform: Option<String>,
+  /// Unset for disabled outputs, as Sway doesn't report it.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  transform: Option<Transform>,
   rect: Rect,
   active: bool,
+  /// Disable this output whenever the one with the given OEM identifier
+  /// is connected.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  disabled_when: Option<String>,
+  /// Replaced by the background, which takes precedence.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  wallpaper: Option<Wallpaper>,
+  /// Drawn by Sway, as an image path followed by a mode such as `fill`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  background: Option<String>,
+  /// Left to Sway when unset.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  scale: Option<f32>,
+  /// Refresh rate of the mode, in millihertz as Sway reports it. Left to
+  /// Sway when unset.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  refresh: Option<u32>,
+  /// Variable refresh rate, left to Sway when unset.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  adaptive_sync: Option<bool>,
+  /// Subpixel layout used for font hinting, such as `rgb` or `vrgb`. Left
+  /// to Sway when unset.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  subpixel: Option<String>,
+  /// Whether an enabled output is lit. Turning it off keeps it in the
+  /// layout, unlike disabling it.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  power: Option<bool>,
+  /// Whether the rect is in physical pixels, as measured from the mode,
+  /// rather than in the logical ones Sway positions outputs with. Logical
+  /// when unset.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  physical: Option<bool>,
+  /// Connector name of the output whose content this one shows, taking
+  /// its position, mode and transform.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  mirror_of: Option<String>,
 }
 
 impl Output {
+  /// The rect in logical pixels: physical ones are divided by the scale.
+  pub fn logical_rect(&self) -> Rect {
+    let scale = match self.scale {
+      Some(scale) if self.is_physical() && scale > 0.0 => scale,
+      _ => return self.rect.clone(),
+    };
+    let logical = |value: f32| (value / scale).round();
+    Rect {
+      x: logical(self.rect.x as f32) as i32,
+      y: logical(self.rect.y as f32) as i32,
+      width: logical(self.rect.width as f32) as u32,
+      height: logical(self.rect.height as f32) as u32,
+    }
+  }
+
+  /// The image Sway draws behind the output followed by its mode, taken
+  /// from the wallpaper of older layouts if there is no background. The
+  /// path is quoted so that spaces and semicolons stay part of it.
+  fn background(&self) -> Option<String> {
+    let (image, mode) = match (&self.background, &self.wallpaper) {
+      (Some(background), _) => split_background(background),
+      (None, Some(w)) => (w.image.clone(), w.mode.clone()),
+      (None, None) => return None,
+    };
+    Some(format!("{} {}", quoted(&image), mode))
+  }
+
+  /// True if the rect is in physical pixels.
+  fn is_physical(&self) -> bool {
+    self.physical == Some(true)
+  }
+
+  /// The connector the output is plugged into.
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// The manufacturer, part of the OEM identifier.
+  pub fn make(&self) -> &str {
+    &self.make
+  }
+
+  /// The model, part of the OEM identifier.
+  pub fn model(&self) -> &str {
+    &self.model
+  }
+
+  /// The serial number, part of the OEM identifier.
+  pub fn serial(&self) -> &str {
+    &self.serial
+  }
+
+  /// Position and resolution.
+  pub fn rect(&self) -> &Rect {
+    &self.rect
+  }
+
+  /// The transform, normal when unset.
+  pub fn transform(&self) -> Option<Transform> {
+    self.transform
+  }
+
+  /// Whether the output is enabled.
+  pub fn active(&self) -> bool {
+    self.active
+  }
+
+  /// Compares the settings of self with another's.
+  fn diff(&self, other: &Self) -> OutputDiff {
+    OutputDiff {
+      name: self.name.clone(),
+      rect: changed(&self.rect, &other.rect),
+      transform: changed(&self.transform, &other.transform),
+      active: changed(&self.active, &other.active),
+      scale: changed_to(&self.scale, &other.scale),
+      refresh: changed_to(&self.refresh, &other.refresh),
+      adaptive_sync: changed_to(&self.adaptive_sync, &other.adaptive_sync),
+      subpixel: changed_to(&self.subpixel, &other.subpixel),
+      power: changed_to(&self.power, &other.power),
+      background: changed(&self.background(), &other.background()),
+      mirror_of: changed(&self.mirror_of, &other.mirror_of),
+    }
+  }
+
   /// Overrides rect and transform values of self with other's.
   fn merge(&mut self, other: &Self) {
     self.active = other.active;
@@ -92,57 +1009,430 @@ impl Output {
     self.rect.y = other.rect.y;
     self.rect.width = other.rect.width;
     self.rect.height = other.rect.height;
-    self.transform = match &other.transform {
-      Some(t) => Some(t.clone()),
-      None => None,
+    self.transform = other.transform;
+    self.disabled_when = other.disabled_when.clone();
+    self.wallpaper = other.wallpaper.clone();
+    self.background = other.background.clone();
+    if other.scale.is_some() {
+      self.scale = other.scale;
+    }
+    if other.refresh.is_some() {
+      self.refresh = other.refresh;
+    }
+    if other.adaptive_sync.is_some() {
+      self.adaptive_sync = other.adaptive_sync;
+    }
+    if other.subpixel.is_some() {
+      self.subpixel = other.subpixel.clone();
+    }
+    self.physical = other.physical;
+    if other.power.is_some() {
+      self.power = other.power;
+    }
+    self.mirror_of = other.mirror_of.clone();
+  }
+
+  /// Overrides the settings that are set.
+  fn overlay(&mut self, overrides: &Overrides) {
+    if let Some(ref rect) = overrides.rect {
+      self.rect = rect.clone();
     }
+    self.transform = overrides.transform.or(self.transform);
+    self.active = overrides.active.unwrap_or(self.active);
+    self.scale = overrides.scale.or(self.scale);
+    self.power = overrides.power.or(self.power);
   }
 }
 
-/// Represents the position and size of an output.
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
-struct Rect {
-  x: u32,
-  y: u32,
-  width: u32,
-  height: u32,
+/// The settings of an output that differ between two layouts, as old
+/// and new values. Unchanged fields are left empty, as are the settings
+/// the new layout leaves unset since they are kept as they are.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OutputDiff {
+  pub name: String,
+  pub rect: Option<(Rect, Rect)>,
+  pub transform: Option<(Option<Transform>, Option<Transform>)>,
+  pub active: Option<(bool, bool)>,
+  pub scale: Option<(Option<f32>, Option<f32>)>,
+  pub refresh: Option<(Option<u32>, Option<u32>)>,
+  pub adaptive_sync: Option<(Option<bool>, Option<bool>)>,
+  pub subpixel: Option<(Option<String>, Option<String>)>,
+  pub power: Option<(Option<bool>, Option<bool>)>,
+  /// The background commands, legacy wallpapers included.
+  pub background: Option<(Option<String>, Option<String>)>,
+  pub mirror_of: Option<(Option<String>, Option<String>)>,
 }
 
-impl Display for Output {
+impl OutputDiff {
+  /// Returns true if no field changed.
+  pub fn is_empty(&self) -> bool {
+    self.rect.is_none()
+      && self.transform.is_none()
+      && self.active.is_none()
+      && self.scale.is_none()
+      && self.refresh.is_none()
+      && self.adaptive_sync.is_none()
+      && self.subpixel.is_none()
+      && self.power.is_none()
+      && self.background.is_none()
+      && self.mirror_of.is_none()
+  }
+}
+
+impl Display for OutputDiff {
+  /// Renders changes as `name: field old -> new, ...`.
   fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-    write!(f, "{}", sway_output_command(&self))
+    let mut changes = Vec::new();
+    if let Some((ref old, ref new)) = self.active {
+      changes.push(format!("{} -> {}", status(*old), status(*new)));
+    }
+    if let Some((ref old, ref new)) = self.rect {
+      if (old.width, old.height) != (new.width, new.height) {
+        changes.push(format!(
+          "res {}x{} -> {}x{}",
+          old.width, old.height, new.width, new.height
+        ));
+      }
+      if (old.x, old.y) != (new.x, new.y) {
+        changes.push(format!("pos {},{} -> {},{}", old.x, old.y, new.x, new.y));
+      }
+    }
+    if let Some((ref old, ref new)) = self.transform {
+      changes.push(format!(
+        "transform {} -> {}",
+        old.map_or("normal", Transform::as_str),
+        new.map_or("normal", Transform::as_str)
+      ));
+    }
+    if let Some((ref old, ref new)) = self.scale {
+      changes.push(format!("scale {}", transition(old, new, f32::to_string)));
+    }
+    if let Some((ref old, ref new)) = self.refresh {
+      let hertz = |mhz: &u32| format!("{}.{:03}Hz", mhz / 1000, mhz % 1000);
+      changes.push(format!("refresh {}", transition(old, new, hertz)));
+    }
+    if let Some((ref old, ref new)) = self.adaptive_sync {
+      let sync = |on: &bool| String::from(if *on { "on" } else { "off" });
+      changes.push(format!("adaptive_sync {}", transition(old, new, sync)));
+    }
+    if let Some((ref old, ref new)) = self.subpixel {
+      changes.push(format!("subpixel {}", transition(old, new, String::clone)));
+    }
+    if let Some((ref old, ref new)) = self.power {
+      let power = |on: &bool| String::from(if *on { "on" } else { "off" });
+      changes.push(format!("power {}", transition(old, new, power)));
+    }
+    if let Some((ref old, ref new)) = self.background {
+      changes.push(format!("bg {}", transition(old, new, String::clone)));
+    }
+    if let Some((ref old, ref new)) = self.mirror_of {
+      changes.push(format!("mirror {}", transition(old, new, String::clone)));
+    }
+    write!(f, "{}: {}", self.name, changes.join(", "))
   }
 }
 
-/// Writes the IPC command corresponding to the output.
-fn sway_output_command(output: &Output) -> String {
-  match output.active {
-    true => format!(
-      "output {} enable res {} pos {} transform {}",
-      output.name,
-      format!("{}x{}", output.rect.width, output.rect.height),
-      format!("{} {}", output.rect.x, output.rect.y),
-      output.transform.as_ref().unwrap_or(&String::from("normal"))
-    ),
-    false => format!("output {} disable", output.name),
+/// Describes whether an output is active.
+fn status(active: bool) -> &'static str {
+  match active {
+    true => "enabled",
+    false => "disabled",
   }
 }
 
-/// Writes an unique string for the output.
-fn unique_oem_identifier(output: &Output) -> String {
-  format!("{}|{}|{}", output.make, output.model, output.serial)
+/// Returns both values if they differ.
+fn changed<T: PartialEq + Clone>(old: &T, new: &T) -> Option<(T, T)> {
+  match old == new {
+    true => None,
+    false => Some((old.clone(), new.clone())),
+  }
 }
 
-#[cfg(test)]
-mod tests {
-  use super::*;
+/// Returns both values if the new one is set and differs, an unset value
+/// leaving the setting as it is.
+fn changed_to<T: PartialEq + Clone>(
+  old: &Option<T>,
+  new: &Option<T>,
+) -> Option<(Option<T>, Option<T>)> {
+  new.as_ref().and_then(|_| changed(old, new))
+}
 
-  #[test]
-  fn it_should_generate_sway_commands_according_to_the_current_layout() {
-    let expected = vec![String::from(
-      "output eDP1 enable res 1920x1080 pos 0 0 transform normal",
-    )];
-    let actual = make_layout().serialize_commands();
+/// Renders a change as `old -> new`, unset values included.
+fn transition<T, F>(old: &Option<T>, new: &Option<T>, show: F) -> String
+where
+  F: Fn(&T) -> String,
+{
+  let shown = |value: &Option<T>| value.as_ref().map_or(String::from("unset"), &show);
+  format!("{} -> {}", shown(old), shown(new))
+}
+
+/// Represents the position and size of an output.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Rect {
+  pub x: i32,
+  pub y: i32,
+  pub width: u32,
+  pub height: u32,
+}
+
+impl Rect {
+  /// True if both rectangles share some area.
+  fn overlaps(&self, other: &Rect) -> bool {
+    self.x < other.right()
+      && other.x < self.right()
+      && self.y < other.bottom()
+      && other.y < self.bottom()
+  }
+
+  /// True if both rectangles share at least a corner.
+  fn touches(&self, other: &Rect) -> bool {
+    self.x <= other.right()
+      && other.x <= self.right()
+      && self.y <= other.bottom()
+      && other.y <= self.bottom()
+  }
+
+  /// First column past the rectangle.
+  fn right(&self) -> i32 {
+    self.x + self.width as i32
+  }
+
+  /// First row past the rectangle.
+  fn bottom(&self) -> i32 {
+    self.y + self.height as i32
+  }
+}
+
+impl Display for Output {
+  fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{}", sway_output_command(&self))
+  }
+}
+
+/// Writes the IPC command corresponding to the output. Disabled outputs
+/// are removed from the layout while powered off ones stay in it, dark.
+fn sway_output_command(output: &Output) -> String {
+  output_command_at(output, &output.logical_rect())
+}
+
+/// Same as sway_output_command, positioning the output at the given
+/// logical rect while keeping the mode in physical pixels.
+fn output_command_at(output: &Output, logical: &Rect) -> String {
+  match output.active {
+    true if output.rect.width == 0 || output.rect.height == 0 => {
+      format!("output {} enable", output.name)
+    }
+    true => format!(
+      "output {} enable res {}{} pos {} transform {}{}{}{}{}",
+      output.name,
+      format!("{}x{}", output.rect.width, output.rect.height),
+      match output.refresh {
+        Some(mhz) => format!("@{}.{:03}Hz", mhz / 1000, mhz % 1000),
+        None => String::new(),
+      },
+      format!("{} {}", logical.x, logical.y),
+      output.transform.map_or("normal", Transform::as_str),
+      match output.scale {
+        Some(scale) => format!(" scale {}", scale),
+        None => String::new(),
+      },
+      match output.adaptive_sync {
+        Some(true) => " adaptive_sync on",
+        Some(false) => " adaptive_sync off",
+        None => "",
+      },
+      match output.subpixel {
+        Some(ref subpixel) => format!(" subpixel {}", subpixel),
+        None => String::new(),
+      },
+      match output.power {
+        Some(false) => " power off",
+        _ => "",
+      }
+    ),
+    false => format!("output {} disable", output.name),
+  }
+}
+
+/// Maps a transform to the ones Sway accepts, reducing degrees modulo 360
+/// so that `-90` becomes `270`. Rotations that are not quarter turns are
+/// rejected.
+pub fn normalize_transform(value: &str) -> Result<String, String> {
+  let (flipped, degrees) = match value {
+    "normal" | "flipped" => return Ok(String::from(value)),
+    _ if value.starts_with("flipped-") => (true, &value["flipped-".len()..]),
+    _ => (false, value),
+  };
+  let turn = match degrees.parse::<i64>() {
+    Ok(n) if n % 90 == 0 => n.rem_euclid(360),
+    _ => return Err(format!("invalid transform: {}", value)),
+  };
+  Ok(match (flipped, turn) {
+    (false, 0) => String::from("normal"),
+    (true, 0) => String::from("flipped"),
+    (false, n) => n.to_string(),
+    (true, n) => format!("flipped-{}", n),
+  })
+}
+
+/// Resets optional fields one by one until the output parses.
+fn lenient_output(mut value: Value, dropped: &mut Vec<String>) -> Option<Output> {
+  let name = String::from(value["name"].as_str().unwrap_or("unknown output"));
+  for field in OPTIONAL_FIELDS {
+    if let Ok(output) = serde_json::from_value(value.clone()) {
+      return Some(output);
+    }
+    match value.get_mut(field) {
+      Some(v) if !v.is_null() => {
+        *v = Value::Null;
+        dropped.push(format!("{}: {}", name, field));
+      }
+      _ => (),
+    }
+  }
+  serde_json::from_value(value)
+    .map_err(|_| dropped.push(name))
+    .ok()
+}
+
+/// Name of the output holding the focused node of a tree, if any.
+pub fn focused_output(tree: &Value) -> Option<String> {
+  children(tree)
+    .filter(|node| node["type"] == "output")
+    .find(|node| holds_focus(node))
+    .and_then(|node| node["name"].as_str().map(String::from))
+}
+
+/// True if the node or any of its descendants has focus.
+fn holds_focus(node: &Value) -> bool {
+  node["focused"] == true || children(node).any(holds_focus)
+}
+
+/// Tiling and floating children of a node.
+fn children(node: &Value) -> impl Iterator<Item = &Value> {
+  ["nodes", "floating_nodes"]
+    .iter()
+    .filter_map(move |key| node[*key].as_array())
+    .flatten()
+}
+
+/// Brings settings Sway reports in its own shape next to the other ones:
+/// the size and refresh rate of the current mode, the adaptive sync
+/// status and the subpixel hinting, unless unknown. Sway reports rects in
+/// logical pixels, so taking the size of the mode makes them physical.
+pub fn lift_reported_settings(mut value: Value) -> Value {
+  if let Value::Array(ref mut outputs) = value {
+    for output in outputs.iter_mut().filter_map(Value::as_object_mut) {
+      if !output.contains_key("physical") {
+        lift_mode(output);
+      }
+      let refresh = output
+        .get("current_mode")
+        .and_then(|mode| mode.get("refresh"))
+        .cloned();
+      if let (Some(refresh), false) = (refresh, output.contains_key("refresh")) {
+        output.insert(String::from("refresh"), refresh);
+      }
+      let adaptive_sync = match output.get("adaptive_sync_status").and_then(Value::as_str) {
+        Some("enabled") => Some(true),
+        Some("disabled") => Some(false),
+        _ => None,
+      };
+      if let (Some(on), false) = (adaptive_sync, output.contains_key("adaptive_sync")) {
+        output.insert(String::from("adaptive_sync"), Value::Bool(on));
+      }
+      let subpixel = match output.get("subpixel_hinting").and_then(Value::as_str) {
+        Some(hint @ ("rgb" | "bgr" | "vrgb" | "vbgr" | "none")) => Some(String::from(hint)),
+        _ => None,
+      };
+      if let (Some(hint), false) = (subpixel, output.contains_key("subpixel")) {
+        output.insert(String::from("subpixel"), Value::String(hint));
+      }
+    }
+  }
+  value
+}
+
+/// Measures the rect of an output in physical pixels with its current
+/// mode, scaling its position to match. Rotated outputs are as high as
+/// their mode is wide.
+fn lift_mode(output: &mut serde_json::Map<String, Value>) {
+  let mode = match output.get("current_mode") {
+    Some(mode) => (mode["width"].as_u64(), mode["height"].as_u64()),
+    None => return,
+  };
+  let scale = output
+    .get("scale")
+    .and_then(Value::as_f64)
+    .filter(|scale| *scale > 0.0)
+    .unwrap_or(1.0);
+  let rotated = matches!(
+    output.get("transform").and_then(Value::as_str),
+    Some("90" | "270" | "flipped-90" | "flipped-270")
+  );
+  let (width, height) = match (mode, rotated) {
+    ((Some(width), Some(height)), false) => (width, height),
+    ((Some(width), Some(height)), true) => (height, width),
+    _ => return,
+  };
+  let rect = match output.get_mut("rect").and_then(Value::as_object_mut) {
+    Some(rect) => rect,
+    None => return,
+  };
+  for key in &["x", "y"] {
+    if let Some(at) = rect.get(*key).and_then(Value::as_f64) {
+      rect.insert(String::from(*key), Value::from((at * scale).round() as i64));
+    }
+  }
+  rect.insert(String::from("width"), Value::from(width));
+  rect.insert(String::from("height"), Value::from(height));
+  output.insert(String::from("physical"), Value::Bool(true));
+}
+
+/// Writes an unique string for the output.
+fn unique_oem_identifier(output: &Output) -> String {
+  format!("{}|{}|{}", output.make, output.model, output.serial)
+}
+
+/// Writes a string shared by every monitor of the same model.
+fn model_identifier(output: &Output) -> String {
+  format!("{}|{}", output.make, output.model)
+}
+
+/// The command drawing the background of an enabled output, if it has
+/// one.
+fn background_command(output: &Output) -> Option<String> {
+  match (output.active, output.background()) {
+    (true, Some(background)) => Some(format!("output {} bg {}", output.name, background)),
+    _ => None,
+  }
+}
+
+/// Splits a background into the image path and what follows it, the mode
+/// and an optional fallback color. Without a mode, it is all a path.
+fn split_background(background: &str) -> (String, String) {
+  let words: Vec<&str> = background.split(' ').collect();
+  match words.iter().rposition(|w| BACKGROUND_MODES.contains(w)) {
+    Some(i) if i > 0 => (words[..i].join(" "), words[i..].join(" ")),
+    _ => (String::from(background), String::from("fill")),
+  }
+}
+
+/// Quotes a path for use in a command.
+fn quoted(path: &str) -> String {
+  format!("\"{}\"", path.replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_should_generate_sway_commands_according_to_the_current_layout() {
+    let expected = vec![String::from(
+      "output eDP1 enable res 1920x1080 pos 0 0 transform normal",
+    )];
+    let actual = make_layout().serialize_commands();
     assert_eq!(expected, actual);
   }
 
@@ -160,8 +1450,8 @@ mod tests {
   #[test]
   fn it_should_handle_multiple_displays_with_disabled_outputs() {
     let expected = vec![
-      String::from("output eDP1 enable res 1920x1080 pos 0 0 transform normal"),
       String::from("output HDMI-2 disable"),
+      String::from("output eDP1 enable res 1920x1080 pos 0 0 transform normal"),
     ];
     let mut l = make_multi_outputs_layout();
     l.0[0].transform = None;
@@ -169,6 +1459,77 @@ mod tests {
     assert_eq!(expected, actual);
   }
 
+  #[test]
+  fn outputs_should_be_disabled_before_others_are_enabled() {
+    let mut l = make_multi_outputs_layout();
+    let mut third = make_output();
+    third.name = String::from("DP-3");
+    third.serial = String::from("67890");
+    third.active = false;
+    l.0.insert(0, third);
+    l.0.reverse();
+    assert_eq!(
+      vec![
+        "output HDMI-2 disable",
+        "output DP-3 disable",
+        "output eDP1 enable res 1920x1080 pos 0 0 transform normal",
+      ],
+      l.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn the_output_holding_focus_should_be_activated() {
+    let tree = serde_json::json!({
+      "type": "root",
+      "nodes": [
+        {"type": "output", "name": "__i3", "nodes": []},
+        {"type": "output", "name": "eDP1", "nodes": [{"type": "workspace", "nodes": []}]},
+        {"type": "output", "name": "HDMI-2", "nodes": [
+          {"type": "workspace", "nodes": [], "floating_nodes": [{"type": "con", "focused": true}]}
+        ]}
+      ]
+    });
+    assert_eq!(Some(String::from("HDMI-2")), focused_output(&tree));
+    let actual = make_multi_outputs_layout().activate_focused(&tree);
+    assert!(actual.0[1].active);
+    assert_eq!(None, focused_output(&Value::Null));
+    let unchanged = make_multi_outputs_layout().activate_focused(&Value::Null);
+    assert_eq!(make_multi_outputs_layout(), unchanged);
+  }
+
+  #[test]
+  fn overlapping_outputs_should_be_invalid() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].active = true;
+    l.0[1].rect.x = 1000;
+    assert_eq!(Err(String::from("eDP1 overlaps HDMI-2")), l.validate());
+  }
+
+  #[test]
+  fn adjacent_outputs_should_be_valid() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].active = true;
+    l.0[1].rect.x = 1920;
+    assert_eq!(Ok(()), l.validate());
+    l.0[1].rect.x = 0;
+    l.0[1].rect.y = -1080;
+    assert_eq!(Ok(()), l.validate());
+  }
+
+  #[test]
+  fn stranded_outputs_should_be_invalid() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].active = true;
+    l.0[1].rect.x = 2560;
+    assert_eq!(
+      Err(String::from("HDMI-2 does not touch any other output")),
+      l.validate()
+    );
+    l.0[1].active = false;
+    assert_eq!(Ok(()), l.validate());
+  }
+
   #[test]
   fn it_should_activate_any_single_output() {
     let expected = vec![String::from(
@@ -185,16 +1546,66 @@ mod tests {
     let l1 = make_multi_outputs_layout();
     let mut l2 = make_multi_outputs_layout();
     l2.0.reverse();
-    assert_eq!(l1.fingerprint(), l2.fingerprint());
+    for mode in [FingerprintMode::Strict, FingerprintMode::ModelOnly] {
+      assert_eq!(l1.fingerprint_with(mode), l2.fingerprint_with(mode));
+    }
+  }
+
+  #[test]
+  fn fingerprint_should_ignore_serials_in_model_only_mode() {
+    let l1 = make_layout();
+    let mut l2 = make_layout();
+    l2.0[0].serial = String::from("67890");
+    assert_ne!(l1.fingerprint(), l2.fingerprint());
+    assert_eq!(
+      l1.fingerprint_with(FingerprintMode::ModelOnly),
+      l2.fingerprint_with(FingerprintMode::ModelOnly)
+    );
+  }
+
+  #[test]
+  fn merge_should_fall_back_to_an_output_of_the_same_model_in_model_only_mode() {
+    let l1 = make_layout();
+    let mut l2 = make_layout();
+    l2.0[0].serial = String::from("67890");
+    l2.0[0].rect.x = 100;
+    let merged = l1
+      .clone()
+      .merge_with(
+        l2.clone(),
+        MergePolicy::Override,
+        FingerprintMode::ModelOnly,
+      )
+      .unwrap();
+    assert_eq!(100, merged.0[0].rect.x);
+    assert!(l1.merge(l2).is_err());
+  }
+
+  #[test]
+  fn identical_monitors_should_not_match_the_same_saved_output() {
+    let mut saved = make_multi_outputs_layout();
+    saved.0[1] = saved.0[0].clone();
+    saved.0[1].name = String::from("HDMI-2");
+    saved.0[1].serial = String::from("67890");
+    saved.0[1].rect.x = 1920;
+    let mut live = saved.clone();
+    live.0[0].name = String::from("DP-1");
+    live.0[1].name = String::from("DP-2");
+    live.0[0].serial = String::from("1");
+    live.0[1].serial = String::from("2");
+    let merged = live
+      .merge_with(saved, MergePolicy::Override, FingerprintMode::ModelOnly)
+      .unwrap();
+    assert_eq!(vec![0, 1920], vec![merged.0[0].rect.x, merged.0[1].rect.x]);
   }
 
   #[test]
   fn merge_should_override_transform() {
     let mut l1 = make_layout();
     let mut l2 = make_layout();
-    l2.0[0].transform = Some(String::from("270"));
-    l1 = l1.merge(l2);
-    assert_eq!(Some(String::from("270")), l1.0[0].transform);
+    l2.0[0].transform = Some(Transform::Rot270);
+    l1 = l1.merge(l2).unwrap();
+    assert_eq!(Some(Transform::Rot270), l1.0[0].transform);
   }
 
   #[test]
@@ -207,7 +1618,7 @@ mod tests {
       width: 333,
       height: 444,
     };
-    l1 = l1.merge(l2);
+    l1 = l1.merge(l2).unwrap();
     assert_eq!(111, l1.0[0].rect.x);
     assert_eq!(222, l1.0[0].rect.y);
     assert_eq!(333, l1.0[0].rect.width);
@@ -219,26 +1630,997 @@ mod tests {
     let mut l1 = make_layout();
     let mut l2 = make_layout();
     l2.0[0].active = false;
-    l1 = l1.merge(l2);
+    l1 = l1.merge(l2).unwrap();
     assert!(!l1.0[0].active);
   }
 
+  #[test]
+  fn swapped_connectors_should_keep_settings_with_their_monitor() {
+    let monitor = |name: &str, serial: &str, x: i32| {
+      let mut o = make_output();
+      o.name = String::from(name);
+      o.serial = String::from(serial);
+      o.rect.x = x;
+      o
+    };
+    let saved = Layout(vec![monitor("DP-1", "A", 0), monitor("DP-2", "B", 1920)]);
+    let live = Layout(vec![monitor("DP-1", "B", 0), monitor("DP-2", "A", 0)]);
+    assert_eq!(saved.fingerprint(), live.fingerprint());
+    assert_eq!(
+      vec![
+        "output DP-1 enable res 1920x1080 pos 1920 0 transform normal",
+        "output DP-2 enable res 1920x1080 pos 0 0 transform normal",
+      ],
+      live.merge(saved).unwrap().serialize_commands()
+    );
+  }
+
+  #[test]
+  fn a_fractional_scale_should_be_read_and_emitted() {
+    let input = r#"[{
+      "name": "eDP1", "make": "Samsung", "model": "XYZ", "serial": "12345",
+      "transform": "normal", "active": true, "scale": 1.5,
+      "rect": {"x": 0, "y": 0, "width": 2880, "height": 1800}
+    }]"#;
+    let layout: Layout = serde_json::from_str(input).unwrap();
+    assert_eq!(
+      vec!["output eDP1 enable res 2880x1800 pos 0 0 transform normal scale 1.5"],
+      layout.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn the_refresh_rate_should_be_read_from_the_current_mode() {
+    let input = serde_json::json!([{
+      "name": "DP-1", "make": "Samsung", "model": "XYZ", "serial": "12345",
+      "transform": "normal", "active": true, "scale": 2.0,
+      "rect": {"x": 0, "y": 0, "width": 1280, "height": 720},
+      "current_mode": {"width": 2560, "height": 1440, "refresh": 143998}
+    }]);
+    let layout: Layout = serde_json::from_value(super::lift_reported_settings(input)).unwrap();
+    assert_eq!(Some(143998), layout.0[0].refresh);
+    assert_eq!(
+      vec!["output DP-1 enable res 2560x1440@143.998Hz pos 0 0 transform normal scale 2"],
+      layout.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn the_mode_should_be_read_from_the_current_mode_of_scaled_outputs() {
+    let input = serde_json::json!([{
+      "name": "eDP1", "make": "Samsung", "model": "XYZ", "serial": "12345",
+      "transform": "normal", "active": true, "scale": 2.0,
+      "rect": {"x": 0, "y": 0, "width": 1280, "height": 800},
+      "current_mode": {"width": 2560, "height": 1600, "refresh": 60000}
+    }, {
+      "name": "DP-1", "make": "Samsung", "model": "XYZ", "serial": "67890",
+      "transform": "normal", "active": true, "scale": 1.0,
+      "rect": {"x": 1280, "y": 0, "width": 1920, "height": 1080},
+      "current_mode": {"width": 1920, "height": 1080, "refresh": 60000}
+    }]);
+    let layout: Layout = serde_json::from_value(super::lift_reported_settings(input)).unwrap();
+    assert_eq!(Ok(()), layout.validate());
+    assert_eq!(
+      vec![
+        "output eDP1 enable res 2560x1600@60.000Hz pos 0 0 transform normal scale 2",
+        "output DP-1 enable res 1920x1080@60.000Hz pos 1280 0 transform normal scale 1",
+      ],
+      layout.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn merge_should_override_the_refresh_rate_but_not_the_fingerprint() {
+    let mut live = make_layout();
+    live.0[0].refresh = Some(60000);
+    let mut saved = make_layout();
+    saved.0[0].refresh = Some(144000);
+    assert_eq!(live.fingerprint(), saved.fingerprint());
+    assert_eq!(
+      Some(60000),
+      live
+        .clone()
+        .merge_with(
+          saved.clone(),
+          MergePolicy::KeepModes,
+          FingerprintMode::Strict
+        )
+        .unwrap()
+        .0[0]
+        .refresh
+    );
+    assert_eq!(Some(144000), live.merge(saved).unwrap().0[0].refresh);
+  }
+
+  #[test]
+  fn merge_should_keep_the_live_refresh_rate_if_none_was_saved() {
+    let mut live = make_layout();
+    live.0[0].refresh = Some(60000);
+    let merged = live.clone().merge(make_layout()).unwrap();
+    assert_eq!(Some(60000), merged.0[0].refresh);
+    assert!(live.delta(&merged).is_empty());
+  }
+
+  #[test]
+  fn adaptive_sync_should_be_read_from_its_status_and_emitted() {
+    let input = serde_json::json!([{
+      "name": "DP-1", "make": "Samsung", "model": "XYZ", "serial": "12345",
+      "transform": "normal", "active": true, "adaptive_sync_status": "enabled",
+      "rect": {"x": 0, "y": 0, "width": 2560, "height": 1440}
+    }]);
+    let layout: Layout = serde_json::from_value(super::lift_reported_settings(input)).unwrap();
+    assert_eq!(
+      vec!["output DP-1 enable res 2560x1440 pos 0 0 transform normal adaptive_sync on"],
+      layout.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn merge_should_copy_the_saved_adaptive_sync() {
+    let mut live = make_layout();
+    live.0[0].adaptive_sync = Some(false);
+    let mut saved = make_layout();
+    saved.0[0].adaptive_sync = Some(true);
+    let actual = live.clone().merge(saved).unwrap().serialize_commands();
+    assert!(actual[0].ends_with(" adaptive_sync on"));
+    let kept = live.clone().merge(make_layout()).unwrap();
+    assert!(live.delta(&kept).is_empty());
+  }
+
+  #[test]
+  fn subpixel_hinting_should_be_read_and_emitted_unless_unknown() {
+    let input = serde_json::json!([{
+      "name": "eDP1", "make": "Samsung", "model": "XYZ", "serial": "12345",
+      "transform": "normal", "active": true, "subpixel_hinting": "vrgb",
+      "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080}
+    }, {
+      "name": "DP-1", "make": "Samsung", "model": "XYZ", "serial": "67890",
+      "transform": "normal", "active": true, "subpixel_hinting": "unknown",
+      "rect": {"x": 1920, "y": 0, "width": 1920, "height": 1080}
+    }]);
+    let layout: Layout = serde_json::from_value(super::lift_reported_settings(input)).unwrap();
+    assert_eq!(
+      vec![
+        "output eDP1 enable res 1920x1080 pos 0 0 transform normal subpixel vrgb",
+        "output DP-1 enable res 1920x1080 pos 1920 0 transform normal",
+      ],
+      layout.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn merge_should_copy_the_saved_subpixel_but_not_fingerprint_it() {
+    let live = make_layout();
+    let mut saved = make_layout();
+    saved.0[0].subpixel = Some(String::from("bgr"));
+    assert_eq!(live.fingerprint(), saved.fingerprint());
+    let actual = live.merge(saved).unwrap().serialize_commands();
+    assert!(actual[0].ends_with(" subpixel bgr"));
+  }
+
+  #[test]
+  fn merge_should_keep_the_live_subpixel_if_none_was_saved() {
+    let mut live = make_layout();
+    live.0[0].subpixel = Some(String::from("rgb"));
+    let merged = live.clone().merge(make_layout()).unwrap();
+    assert_eq!(Some(String::from("rgb")), merged.0[0].subpixel);
+    assert!(live.delta(&merged).is_empty());
+  }
+
+  #[test]
+  fn merge_should_copy_the_saved_scale() {
+    let mut live = make_layout();
+    live.0[0].scale = Some(1.0);
+    let mut saved = make_layout();
+    saved.0[0].scale = Some(1.5);
+    assert_eq!(Some(1.5), live.clone().merge(saved).unwrap().0[0].scale);
+    assert_eq!(Some(1.0), live.merge(make_layout()).unwrap().0[0].scale);
+  }
+
   #[test]
   fn merge_should_not_override_name() {
     let mut l1 = make_layout();
     let mut l2 = make_layout();
     l2.0[0].name = String::from("HDMI-2");
-    l1 = l1.merge(l2);
+    l1 = l1.merge(l2).unwrap();
     assert_eq!(String::from("eDP1"), l1.0[0].name);
   }
 
   #[test]
-  #[should_panic]
-  fn merge_should_panic_in_case_of_incompatible_layouts() {
+  fn outputs_without_a_mode_should_be_enabled_as_sway_likes() {
+    let mut l = make_layout();
+    l.0[0].rect.width = 0;
+    l.0[0].rect.height = 0;
+    assert_eq!(vec!["output eDP1 enable"], l.serialize_commands());
+  }
+
+  #[test]
+  fn merge_should_fail_in_case_of_incompatible_layouts() {
     let l1 = make_layout();
     let mut l2 = make_layout();
     l2.0[0].make = String::from("Apple");
-    l1.merge(l2);
+    assert_eq!(
+      Err(super::MergeError(String::from("Samsung|XYZ|12345"))),
+      l1.merge(l2)
+    );
+  }
+
+  #[test]
+  fn merge_should_leave_outputs_missing_from_the_saved_layout_alone() {
+    let mut live = make_multi_outputs_layout();
+    live.0[1].model = String::from("Projector");
+    let mut saved = make_layout();
+    saved.0[0].rect.x = 1920;
+    let actual = live.clone().merge(saved).unwrap();
+    assert_eq!(1920, actual.0[0].rect.x);
+    assert_eq!(live.0[1], actual.0[1]);
+  }
+
+  #[test]
+  fn merge_should_ignore_saved_outputs_that_are_not_connected() {
+    let live = make_layout();
+    let mut saved = make_multi_outputs_layout();
+    saved.0[0].transform = Some(Transform::Rot90);
+    let actual = live.merge(saved).unwrap();
+    assert_eq!(1, actual.len());
+    assert_eq!(Some(Transform::Rot90), actual.0[0].transform);
+  }
+
+  #[test]
+  fn pinned_output_should_ignore_saved_position() {
+    let expected = vec![String::from(
+      "output eDP1 enable res 1280x1024 pos 3840 0 transform normal",
+    )];
+    let mut saved = make_layout();
+    saved.0[0].rect.x = 1920;
+    let mut pins = super::Pins::new();
+    pins.insert(
+      String::from("eDP1"),
+      super::Rect {
+        x: 3840,
+        y: 0,
+        width: 1280,
+        height: 1024,
+      },
+    );
+    let actual = make_layout()
+      .merge(saved)
+      .unwrap()
+      .pin(&pins)
+      .serialize_commands();
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn outputs_can_be_pinned_by_oem_identifier() {
+    let mut pins = super::Pins::new();
+    pins.insert(
+      String::from("Apple|XYZ|12345"),
+      super::Rect {
+        x: 0,
+        y: 1080,
+        width: 1920,
+        height: 1080,
+      },
+    );
+    let l = make_multi_outputs_layout().pin(&pins);
+    assert_eq!(0, l.0[0].rect.y);
+    assert_eq!(1080, l.0[1].rect.y);
+  }
+
+  #[test]
+  fn a_chain_of_fragments_should_stack_with_the_later_winning() {
+    let base: super::Fragment = serde_json::from_str(
+      r#"{"Samsung|XYZ|12345": {"rect": {"x": 1920, "y": 0, "width": 1920, "height": 1080}, "scale": 1.0}}"#,
+    )
+    .unwrap();
+    let context: super::Fragment =
+      serde_json::from_str(r#"{"eDP1": {"scale": 1.5, "transform": "90"}}"#).unwrap();
+    let actual = make_layout().overlay(&base).overlay(&context);
+    assert_eq!(
+      vec!["output eDP1 enable res 1920x1080 pos 1920 0 transform 90 scale 1.5"],
+      actual.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn a_fragment_should_leave_unset_fields_alone() {
+    let fragment: super::Fragment =
+      serde_json::from_str(r#"{"eDP1": {"active": false}, "HDMI-9": {"active": true}}"#).unwrap();
+    let mut expected = make_layout();
+    expected.0[0].active = false;
+    assert_eq!(expected, make_layout().overlay(&fragment));
+  }
+
+  #[test]
+  fn ranking_should_put_the_exact_match_first() {
+    let mut other = make_output();
+    other.serial = String::from("67890");
+    let saved = vec![
+      (String::from("a"), Layout(vec![make_output(), other])),
+      (String::from("b"), make_multi_outputs_layout()),
+    ];
+    let actual = make_multi_outputs_layout().rank(saved);
+    assert_eq!(String::from("b"), actual[0].0);
+    assert_eq!(super::Score::Exact, actual[0].1);
+    assert_eq!(super::Score::Partial(1), actual[1].1);
+  }
+
+  #[test]
+  fn ranking_should_order_partial_matches_by_overlap() {
+    let mut o1 = make_output();
+    o1.serial = String::from("1");
+    let mut o2 = make_output();
+    o2.serial = String::from("2");
+    let mut o3 = make_output();
+    o3.serial = String::from("3");
+    let current = Layout(vec![o1.clone(), o2.clone(), o3.clone()]);
+    let saved = vec![
+      (String::from("none"), make_layout()),
+      (String::from("one"), Layout(vec![o1.clone()])),
+      (String::from("two"), Layout(vec![o1, o2])),
+    ];
+    let actual: Vec<String> = current.rank(saved).into_iter().map(|r| r.0).collect();
+    assert_eq!(vec!["two", "one", "none"], actual);
+  }
+
+  #[test]
+  fn duplicate_connector_names_should_keep_the_active_output() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].name = String::from("eDP1");
+    l.0[0].active = false;
+    l.0[1].active = true;
+    let actual = l.dedup_names();
+    assert_eq!(1, actual.0.len());
+    assert_eq!(String::from("Apple"), actual.0[0].make);
+  }
+
+  #[test]
+  fn duplicate_connector_names_should_keep_the_first_output_on_a_tie() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].name = String::from("eDP1");
+    l.0[1].active = true;
+    let actual = l.dedup_names();
+    assert_eq!(1, actual.0.len());
+    assert_eq!(String::from("Samsung"), actual.0[0].make);
+  }
+
+  #[test]
+  fn distinct_connector_names_should_be_left_untouched() {
+    let actual = make_multi_outputs_layout().dedup_names();
+    assert_eq!(2, actual.0.len());
+  }
+
+  #[test]
+  fn diff_should_report_changed_rect_and_transform() {
+    let current = make_layout();
+    let mut target = make_layout();
+    target.0[0].rect.x = 1920;
+    target.0[0].transform = Some(Transform::Rot90);
+    let actual = current.diff(&target);
+    assert_eq!(1, actual.len());
+    assert_eq!(String::from("eDP1"), actual[0].name);
+    assert_eq!(
+      Some((current.0[0].rect.clone(), target.0[0].rect.clone())),
+      actual[0].rect
+    );
+    assert_eq!(
+      Some((Some(Transform::Normal), Some(Transform::Rot90))),
+      actual[0].transform
+    );
+    assert_eq!(None, actual[0].active);
+  }
+
+  #[test]
+  fn diff_should_report_every_setting_sent_to_sway() {
+    let current = make_layout();
+    let mut target = make_layout();
+    target.0[0].scale = Some(2.0);
+    target.0[0].refresh = Some(59951);
+    target.0[0].adaptive_sync = Some(true);
+    target.0[0].subpixel = Some(String::from("rgb"));
+    target.0[0].power = Some(false);
+    target.0[0].background = Some(String::from("/tmp/a.png fit"));
+    target.0[0].mirror_of = Some(String::from("HDMI-2"));
+    let actual = current.diff(&target);
+    assert_eq!(1, actual.len());
+    assert_eq!(
+      "eDP1: scale unset -> 2, refresh unset -> 59.951Hz, adaptive_sync unset -> on, \
+       subpixel unset -> rgb, power unset -> off, bg unset -> \"/tmp/a.png\" fit, \
+       mirror unset -> HDMI-2",
+      actual[0].to_string()
+    );
+  }
+
+  #[test]
+  fn diff_should_ignore_settings_left_unset() {
+    let mut current = make_layout();
+    current.0[0].scale = Some(2.0);
+    current.0[0].refresh = Some(60000);
+    assert!(current.diff(&make_layout()).is_empty());
+  }
+
+  #[test]
+  fn diff_should_be_empty_for_identical_outputs() {
+    let actual = make_multi_outputs_layout().diff(&make_multi_outputs_layout());
+    assert!(actual.is_empty());
+  }
+
+  #[test]
+  fn lenient_parsing_should_reset_unknown_transform_values() {
+    let input = serde_json::json!([
+      {
+        "name": "eDP1",
+        "make": "Samsung",
+        "model": "XYZ",
+        "serial": "12345",
+        "transform": {"degrees": 90},
+        "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+        "active": true
+      }
+    ]);
+    let (actual, dropped) = super::Layout::lenient(input).unwrap();
+    assert_eq!(vec![String::from("eDP1: transform")], dropped);
+    assert_eq!(
+      vec![String::from(
+        "output eDP1 enable res 1920x1080 pos 0 0 transform normal"
+      )],
+      actual.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn lenient_parsing_should_skip_unusable_outputs() {
+    let input = serde_json::json!([
+      serde_json::to_value(make_output()).unwrap(),
+      {"name": "HDMI-2", "rect": "invalid"}
+    ]);
+    let (actual, dropped) = super::Layout::lenient(input).unwrap();
+    assert_eq!(vec![String::from("HDMI-2")], dropped);
+    assert_eq!(1, actual.0.len());
+  }
+
+  #[test]
+  fn rules_should_disable_an_output_when_the_other_is_connected() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].active = true;
+    l.0[0].disabled_when = Some(String::from("Apple|XYZ|12345"));
+    let actual = l.apply_rules();
+    assert!(!actual.0[0].active);
+    assert!(actual.0[1].active);
+  }
+
+  #[test]
+  fn rules_should_leave_an_output_enabled_when_the_other_is_absent() {
+    let mut l = make_layout();
+    l.0[0].disabled_when = Some(String::from("Apple|XYZ|12345"));
+    let actual = l.apply_rules();
+    assert!(actual.0[0].active);
+  }
+
+  #[test]
+  fn merge_should_copy_activation_rules() {
+    let mut saved = make_layout();
+    saved.0[0].disabled_when = Some(String::from("Apple|XYZ|12345"));
+    let actual = make_layout().merge(saved).unwrap();
+    assert_eq!(
+      Some(String::from("Apple|XYZ|12345")),
+      actual.0[0].disabled_when
+    );
+  }
+
+  #[test]
+  fn a_default_arrangement_should_not_differ_from_defaults() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].active = true;
+    l.0[1].rect.x = 1920;
+    assert!(l.diff(&l.defaults()).is_empty());
+  }
+
+  #[test]
+  fn a_customized_arrangement_should_differ_from_defaults() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].rect.x = 1920;
+    l.0[0].transform = Some(Transform::Rot90);
+    let actual: Vec<String> = l
+      .diff(&l.defaults())
+      .iter()
+      .map(|d| d.to_string())
+      .collect();
+    assert_eq!(
+      vec![
+        String::from("eDP1: pos 1920,0 -> 0,0, transform 90 -> normal"),
+        String::from("HDMI-2: disabled -> enabled, pos 0,0 -> 1920,0"),
+      ],
+      actual
+    );
+  }
+
+  #[test]
+  fn enabled_outputs_should_be_followed_by_their_background() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].background = Some(String::from("/path/img.png fill"));
+    l.0[1].background = Some(String::from("/path/img.png fill"));
+    let actual = l.serialize_commands();
+    assert_eq!(
+      vec![
+        "output HDMI-2 disable",
+        "output eDP1 enable res 1920x1080 pos 0 0 transform normal",
+        "output eDP1 bg \"/path/img.png\" fill",
+      ],
+      actual
+    );
+  }
+
+  #[test]
+  fn delta_should_include_the_background_of_changed_outputs() {
+    let live = make_multi_outputs_layout();
+    let mut target = live.clone();
+    target.0[0].background = Some(String::from("/a.png fill"));
+    assert!(live.delta(&target).is_empty());
+    target.0[0].rect.x = 100;
+    assert_eq!(
+      vec![
+        "output eDP1 enable res 1920x1080 pos 100 0 transform normal",
+        "output eDP1 bg \"/a.png\" fill",
+      ],
+      live.delta(&target)
+    );
+  }
+
+  #[test]
+  fn delta_should_disable_outputs_before_enabling_others() {
+    let mut live = make_multi_outputs_layout();
+    live.0[0].active = false;
+    live.0[1].active = true;
+    let target = make_multi_outputs_layout();
+    assert_eq!(
+      vec![
+        "output HDMI-2 disable",
+        "output eDP1 enable res 1920x1080 pos 0 0 transform normal",
+      ],
+      live.delta(&target)
+    );
+  }
+
+  #[test]
+  fn delta_should_activate_a_single_output() {
+    let mut live = make_layout();
+    live.0[0].active = false;
+    assert_eq!(
+      vec!["output eDP1 enable res 1920x1080 pos 0 0 transform normal"],
+      live.delta(&live.clone())
+    );
+  }
+
+  #[test]
+  fn background_paths_should_be_quoted() {
+    let mut l = make_layout();
+    l.0[0].background = Some(String::from("/my \"walls\"; exit/img.png fit #000000"));
+    assert_eq!(
+      r#"output eDP1 bg "/my \"walls\"; exit/img.png" fit #000000"#,
+      l.serialize_commands()[1]
+    );
+    l.0[0].background = Some(String::from("/img.png"));
+    assert_eq!(
+      r#"output eDP1 bg "/img.png" fill"#,
+      l.serialize_commands()[1]
+    );
+  }
+
+  #[test]
+  fn mirrors_should_be_placed_over_the_output_they_mirror() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].rect.x = 1920;
+    l.0[1].active = true;
+    l.0[1].mirror_of = Some(String::from("eDP1"));
+    assert_eq!(
+      vec![
+        "output eDP1 enable res 1920x1080 pos 1920 0 transform normal",
+        "output HDMI-2 enable res 1920x1080 pos 1920 0 transform normal",
+      ],
+      l.serialize_commands()
+    );
+    assert_eq!(Ok(()), l.validate());
+  }
+
+  #[test]
+  fn mirrors_of_missing_or_disabled_outputs_should_be_refused() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].mirror_of = Some(String::from("HDMI-2"));
+    assert_eq!(
+      Err(String::from("eDP1 mirrors disabled output HDMI-2")),
+      l.validate()
+    );
+    l.0[0].mirror_of = Some(String::from("DP-9"));
+    assert_eq!(
+      Err(String::from("eDP1 mirrors unknown output DP-9")),
+      l.validate()
+    );
+  }
+
+  #[test]
+  fn merge_should_copy_the_mirror_relationship() {
+    let live = make_layout();
+    let mut saved = make_layout();
+    saved.0[0].mirror_of = Some(String::from("HDMI-2"));
+    let actual = live.merge(saved).unwrap();
+    assert_eq!(Some(String::from("HDMI-2")), actual.0[0].mirror_of);
+  }
+
+  #[test]
+  fn merge_should_copy_the_saved_background_but_not_the_fingerprint() {
+    let live = make_layout();
+    let mut saved = make_layout();
+    saved.0[0].background = Some(String::from("/path/img.png fill"));
+    assert_eq!(live.fingerprint(), saved.fingerprint());
+    let actual = live.merge(saved).unwrap();
+    assert_eq!(
+      Some(String::from("/path/img.png fill")),
+      actual.0[0].background
+    );
+  }
+
+  #[test]
+  fn wallpapers_of_older_layouts_should_be_drawn_by_sway() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].wallpaper = serde_json::from_str(r#"{"image": "/a.png"}"#).unwrap();
+    l.0[1].wallpaper = l.0[0].wallpaper.clone();
+    l.0[1].active = false;
+    assert_eq!(
+      vec![
+        "output HDMI-2 disable",
+        "output eDP1 enable res 1920x1080 pos 0 0 transform normal",
+        "output eDP1 bg \"/a.png\" fill",
+      ],
+      l.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn env_format_should_index_each_output() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].rect.x = 1920;
+    let expected = vec![
+      "AUTOSWAY_OUTPUT_0_NAME=eDP1",
+      "AUTOSWAY_OUTPUT_0_RES=1920x1080",
+      "AUTOSWAY_OUTPUT_0_POS=0,0",
+      "AUTOSWAY_OUTPUT_0_TRANSFORM=normal",
+      "AUTOSWAY_OUTPUT_0_ACTIVE=true",
+      "AUTOSWAY_OUTPUT_1_NAME=HDMI-2",
+      "AUTOSWAY_OUTPUT_1_RES=1920x1080",
+      "AUTOSWAY_OUTPUT_1_POS=1920,0",
+      "AUTOSWAY_OUTPUT_1_TRANSFORM=normal",
+      "AUTOSWAY_OUTPUT_1_ACTIVE=false",
+    ]
+    .join("\n");
+    assert_eq!(expected, l.to_env());
+  }
+
+  #[test]
+  fn transforms_should_be_reduced_to_canonical_degrees() {
+    assert_eq!(Ok(String::from("270")), super::normalize_transform("-90"));
+    assert_eq!(Ok(String::from("90")), super::normalize_transform("450"));
+    assert_eq!(
+      Ok(String::from("normal")),
+      super::normalize_transform("360")
+    );
+    assert_eq!(
+      Ok(String::from("flipped-270")),
+      super::normalize_transform("flipped--90")
+    );
+  }
+
+  #[test]
+  fn transforms_should_be_quarter_turns() {
+    assert!(super::normalize_transform("45").is_err());
+    assert!(super::normalize_transform("upside-down").is_err());
+  }
+
+  #[test]
+  fn layouts_should_normalize_every_transform() {
+    let mut value = serde_json::to_value(make_layout()).unwrap();
+    value[0]["transform"] = serde_json::json!("-90");
+    let actual: Layout = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(Some(Transform::Rot270), actual.0[0].transform);
+    value[0]["transform"] = serde_json::json!("90deg");
+    assert!(serde_json::from_value::<Layout>(value).is_err());
+  }
+
+  #[test]
+  fn setting_a_transform_should_only_rotate_the_named_output() {
+    let l = make_multi_outputs_layout()
+      .set_transform("eDP1", "-90")
+      .unwrap();
+    assert_eq!(Some(Transform::Rot270), l.0[0].transform);
+    assert_eq!(Some(Transform::Normal), l.0[1].transform);
+  }
+
+  #[test]
+  fn setting_a_transform_should_fail_on_an_unknown_output() {
+    assert_eq!(
+      Err(String::from("unknown output: DP-9")),
+      make_layout().set_transform("DP-9", "90")
+    );
+  }
+
+  #[test]
+  fn outputs_should_be_scaled_by_the_highest_threshold_exceeded() {
+    let scaling = vec![
+      super::ScaleThreshold {
+        wider_than: 0,
+        scale: 1.0,
+      },
+      super::ScaleThreshold {
+        wider_than: 3000,
+        scale: 2.0,
+      },
+    ];
+    let mut l = make_multi_outputs_layout();
+    l.0[1].active = true;
+    l.0[1].rect.width = 3840;
+    let actual = l.apply_scaling(&scaling);
+    assert_eq!(Some(1.0), actual.0[0].scale);
+    assert_eq!(Some(2.0), actual.0[1].scale);
+    assert_eq!(
+      "output HDMI-2 enable res 3840x1080 pos 0 0 transform normal scale 2",
+      actual.serialize_commands()[1]
+    );
+  }
+
+  #[test]
+  fn an_explicit_saved_scale_should_override_the_scaling_policy() {
+    let scaling = vec![super::ScaleThreshold {
+      wider_than: 0,
+      scale: 2.0,
+    }];
+    let mut saved = make_layout();
+    saved.0[0].scale = Some(1.5);
+    let actual = make_layout().apply_scaling(&scaling).merge(saved).unwrap();
+    assert_eq!(Some(1.5), actual.0[0].scale);
+    let actual = make_layout()
+      .apply_scaling(&scaling)
+      .merge(make_layout())
+      .unwrap();
+    assert_eq!(Some(2.0), actual.0[0].scale);
+  }
+
+  #[test]
+  fn powered_off_outputs_should_stay_enabled() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].power = Some(false);
+    assert_eq!(
+      vec![
+        "output HDMI-2 disable",
+        "output eDP1 enable res 1920x1080 pos 0 0 transform normal power off",
+      ],
+      l.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn merge_should_keep_the_live_power_state_if_none_was_saved() {
+    let mut live = make_layout();
+    live.0[0].power = Some(false);
+    let merged = live.clone().merge(make_layout()).unwrap();
+    assert_eq!(Some(false), merged.0[0].power);
+    assert!(live.delta(&merged).is_empty());
+  }
+
+  #[test]
+  fn powered_on_outputs_should_not_mention_power() {
+    let mut l = make_layout();
+    l.0[0].power = Some(true);
+    assert_eq!(
+      vec!["output eDP1 enable res 1920x1080 pos 0 0 transform normal"],
+      l.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn usual_resolutions_should_pass_the_bounds_check() {
+    assert_eq!(Ok(()), make_layout().check_resolutions(16384));
+  }
+
+  #[test]
+  fn empty_resolutions_should_fail_the_bounds_check() {
+    let mut l = make_layout();
+    l.0[0].rect.height = 0;
+    assert_eq!(
+      Err(String::from("eDP1: resolution 1920x0 out of bounds")),
+      l.check_resolutions(16384)
+    );
+  }
+
+  #[test]
+  fn huge_resolutions_should_fail_the_bounds_check() {
+    let mut l = make_layout();
+    l.0[0].rect.width = 99999;
+    l.0[0].rect.height = 99999;
+    assert!(l.check_resolutions(16384).is_err());
+  }
+
+  #[test]
+  fn disabled_outputs_should_pass_the_bounds_check() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].rect = Rect {
+      x: 0,
+      y: 0,
+      width: 0,
+      height: 0,
+    };
+    assert_eq!(Ok(()), l.check_resolutions(16384));
+  }
+
+  #[test]
+  fn absurd_scales_should_fail_the_bounds_check() {
+    let mut l = make_layout();
+    l.0[0].scale = Some(0.0);
+    assert_eq!(
+      Err(String::from("eDP1: scale 0 out of bounds")),
+      l.check_resolutions(16384)
+    );
+    l.0[0].scale = Some(100.0);
+    assert!(l.check_resolutions(16384).is_err());
+    l.0[0].scale = Some(2.0);
+    assert_eq!(Ok(()), l.check_resolutions(16384));
+  }
+
+  #[test]
+  fn compact_json_should_fit_on_a_single_line() {
+    let actual = make_layout().to_json(false).unwrap();
+    assert!(actual.starts_with(r#"[{"name":"eDP1","#));
+    assert!(!actual.contains('\n'));
+  }
+
+  #[test]
+  fn pretty_json_should_be_indented() {
+    let actual = make_layout().to_json(true).unwrap();
+    assert!(actual.starts_with("[\n  {\n    \"name\": \"eDP1\","));
+    assert_eq!(make_layout(), serde_json::from_str(&actual).unwrap());
+  }
+
+  #[test]
+  fn json_should_round_trip_every_stored_field() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].background = Some(String::from("/path/img.png fill"));
+    l.0[0].scale = Some(1.5);
+    l.0[0].refresh = Some(59_951);
+    l.0[0].power = Some(false);
+    l.0[1].mirror_of = Some(String::from("eDP1"));
+    l.0[1].disabled_when = Some(String::from("Samsung XYZ 12345"));
+    let actual: super::Layout = serde_json::from_str(&l.to_json(false).unwrap()).unwrap();
+    assert_eq!(l, actual);
+  }
+
+  #[test]
+  fn map_should_draw_side_by_side_outputs() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].rect.width = 400;
+    l.0[0].rect.height = 480;
+    l.0[1].active = true;
+    l.0[1].rect.x = 400;
+    l.0[1].rect.width = 480;
+    l.0[1].rect.height = 480;
+    let expected = ["+---++----+", "|eDP||HDMI|", "+---++----+"].join("\n");
+    assert_eq!(expected, l.to_map());
+  }
+
+  #[test]
+  fn map_should_omit_inactive_outputs() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].rect.width = 400;
+    l.0[0].rect.height = 480;
+    assert_eq!("+---+\n|eDP|\n+---+", l.to_map());
+  }
+
+  #[test]
+  fn recentering_should_move_the_origin_to_zero() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].rect.x = 1920;
+    l.0[1].rect.x = 3840;
+    l.0[1].rect.y = 100;
+    let mut left = make_output();
+    left.serial = String::from("67890");
+    l.0.push(left);
+    let actual = l.recenter("Samsung|XYZ|12345").unwrap();
+    assert_eq!((0, 0), (actual.0[0].rect.x, actual.0[0].rect.y));
+    assert_eq!((1920, 100), (actual.0[1].rect.x, actual.0[1].rect.y));
+    assert_eq!((-1920, 0), (actual.0[2].rect.x, actual.0[2].rect.y));
+  }
+
+  #[test]
+  fn recentering_should_fail_on_an_unknown_origin() {
+    assert!(make_layout().recenter("Foo|Bar|Baz").is_err());
+  }
+
+  #[test]
+  fn physical_rects_should_be_divided_by_the_scale() {
+    let mut o = make_output();
+    o.rect = Rect {
+      x: 1920,
+      y: 0,
+      width: 3840,
+      height: 2160,
+    };
+    assert_eq!(o.rect, o.logical_rect());
+    o.physical = Some(true);
+    o.scale = Some(2.0);
+    let expected = Rect {
+      x: 960,
+      y: 0,
+      width: 1920,
+      height: 1080,
+    };
+    assert_eq!(expected, o.logical_rect());
+  }
+
+  #[test]
+  fn scaled_panels_should_be_positioned_by_logical_coordinates() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].rect.width = 3840;
+    l.0[0].rect.height = 2160;
+    l.0[0].scale = Some(2.0);
+    l.0[1].active = true;
+    l.0[1].rect.x = 3840;
+    for o in l.0.iter_mut() {
+      o.physical = Some(true);
+    }
+    assert_eq!(
+      vec![
+        "output eDP1 enable res 3840x2160 pos 0 0 transform normal scale 2",
+        "output HDMI-2 enable res 1920x1080 pos 1920 0 transform normal",
+      ],
+      l.serialize_commands()
+    );
+    assert_eq!(Ok(()), l.validate());
+  }
+
+  #[test]
+  fn panels_right_of_a_regular_monitor_should_keep_touching_it() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].active = true;
+    l.0[1].rect = Rect {
+      x: 1920,
+      y: 0,
+      width: 3840,
+      height: 2160,
+    };
+    l.0[1].scale = Some(2.0);
+    for o in l.0.iter_mut() {
+      o.physical = Some(true);
+    }
+    assert_eq!(
+      "output HDMI-2 enable res 3840x2160 pos 1920 0 transform normal scale 2",
+      l.serialize_commands()[1]
+    );
+  }
+
+  #[test]
+  fn reanchoring_should_follow_the_width_of_the_left_neighbor() {
+    let mut l = make_multi_outputs_layout();
+    l.0[0].rect.width = 2560;
+    l.0[1].active = true;
+    l.0[1].rect.x = 1920;
+    let actual = l.reanchor();
+    assert_eq!(0, actual.0[0].rect.x);
+    assert_eq!(2560, actual.0[1].rect.x);
+  }
+
+  #[test]
+  fn reanchoring_should_keep_stacked_outputs_aligned() {
+    let mut l = make_multi_outputs_layout();
+    l.0[1].active = true;
+    l.0[1].rect.y = 1080;
+    l.0[1].rect.width = 2560;
+    let mut right = make_output();
+    right.serial = String::from("67890");
+    right.rect.x = 1920;
+    l.0.push(right);
+    let actual = l.reanchor();
+    assert_eq!(0, actual.0[0].rect.x);
+    assert_eq!(0, actual.0[1].rect.x);
+    assert_eq!(2560, actual.0[2].rect.x);
   }
 
   fn make_layout() -> super::Layout {
@@ -260,7 +2642,7 @@ mod tests {
       make: String::from("Samsung"),
       model: String::from("XYZ"),
       serial: String::from("12345"),
-      transform: Some(String::from("normal")),
+      transform: Some(Transform::Normal),
       rect: super::Rect {
         x: 0,
         y: 0,
@@ -268,6 +2650,16 @@ mod tests {
         height: 1080,
       },
       active: true,
+      disabled_when: None,
+      wallpaper: None,
+      background: None,
+      scale: None,
+      refresh: None,
+      adaptive_sync: None,
+      subpixel: None,
+      physical: None,
+      power: None,
+      mirror_of: None,
     }
   }
 }
@@ -1,98 +1,1500 @@
+mod clock;
+mod config;
+mod confirm;
+mod input;
 mod ipc;
 mod layout;
 mod message;
+mod profile;
 mod repository;
+mod saved;
+mod watch;
+mod workspace;
 
-use ipc::Ipc;
-use layout::Layout;
+pub use config::Config;
+pub use layout::{
+  FingerprintMode, Layout, MergeError, MergePolicy, Output, OutputDiff, Rect, Transform,
+};
+pub use profile::Profile;
+pub use repository::Format as StoreFormat;
+
+use clock::Clock;
+use confirm::{Confirmation, Sentinel};
+use input::Input;
+use ipc::{Ipc, Transport};
+use layout::{Fragment, Pins, Scaling, Score};
 use message::{Message, Response};
-use repository::Repository;
+use repository::{Repository, StorageError};
+use saved::{Saved, Stored};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::error;
 use std::fmt;
+use std::fs;
 use std::io;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use watch::Events;
+use workspace::Workspace;
+
+/// Storage identifier of the outputs pinned to a fixed position.
+const PINS: &str = "pins";
+
+/// Storage identifier of the scale factors given by output width.
+const SCALING: &str = "scaling";
+
+/// Storage identifier of the fragments stacked over saved layouts, unless
+/// given on the command line.
+const FRAGMENTS: &str = "fragments";
+
+/// Storage identifier of the digest of what was last applied to each
+/// Sway instance.
+const LAST_APPLIED: &str = "last-applied";
+
+/// Storage identifier of the state watch mode left each Sway instance in,
+/// hidden so that it is never listed along with layouts.
+const WATCH_STATE: &str = ".watch-state";
+
+/// Largest width or height accepted from saved layouts unless
+/// configured otherwise.
+const MAX_RESOLUTION: u32 = 16384;
+
+/// Outputs answered by the internal peer of the self-test.
+const SELF_TEST_OUTPUTS: &[u8] = br#"[{"name": "eDP1", "make": "Test", "model": "Loopback",
+  "serial": "0", "transform": "normal", "active": true,
+  "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080}}]"#;
+
+/// Storage identifier of the file confirming a layout being tried.
+const CONFIRMED: &str = "confirmed";
 
 /// Tells the program what to do.
+#[derive(Clone)]
 pub enum Action {
   /// Automatically configure layout.
   Auto,
-  /// Record current layout for future detection.
-  Save,
+  /// Record current layout for future detection, or under a name to
+  /// apply it on demand.
+  Save(Option<String>),
+  /// Configure the layout saved under a name over the current one.
+  Apply(String),
   /// List outputs of the current layout.
-  List,
+  List(Format),
+  /// Rank saved layouts by how well they match the current one.
+  Rank,
+  /// List saved layouts, optionally only those recorded recently.
+  Saved(Option<Duration>),
+  /// Automatically configure layout whenever outputs change.
+  Watch,
+  /// Show how the current layout differs from Sway's defaults.
+  Defaults,
+  /// Draw the current layout as a map of output rectangles.
+  Map,
+  /// Configure layout, reverting to the previous one unless confirmed
+  /// within the given delay.
+  Try(Duration),
+  /// Keep the layout being tried.
+  Confirm,
+  /// Rotate a single output, given its connector name and transform.
+  Transform(String, String),
+  /// Turn off a single output, given its connector name.
+  Disable(String),
+  /// Turn a single output back on where it was saved, given its
+  /// connector name.
+  Enable(String),
+  /// Rewrite every stored file in its canonical form.
+  Normalize,
+  /// Forget the layout saved for the current one.
+  Delete,
+  /// List workspaces by the output they are on.
+  Workspaces,
+  /// Print the commands auto would send, without sending them.
+  DryRun,
+  /// Show how the current layout differs from the saved one.
+  Diff,
+  /// Print when, where and with which schema a layout was saved, given
+  /// its fingerprint.
+  Info(String),
+  /// Print the fingerprint of the current layout.
+  Fingerprint,
+  /// Print the layout saved under a fingerprint as Sway commands, after
+  /// when and where it was saved.
+  Show(String),
+  /// Save the layout found in a JSON file under its fingerprint, given
+  /// the path of the file.
+  Import(String),
+  /// Write the layout saved under a fingerprint to a JSON file, given the
+  /// fingerprint and the path of the file.
+  Export(String, String),
+}
+
+/// How layouts are printed.
+#[derive(Clone)]
+pub enum Format {
+  /// Sway commands.
+  Text,
+  /// Shell variable assignments.
+  Env,
+  /// The outputs as reported by Sway.
+  Json(JsonStyle),
+}
+
+/// How JSON is laid out.
+#[derive(Clone)]
+pub enum JsonStyle {
+  /// Indented, for reading.
+  Pretty,
+  /// On a single line, for piping.
+  Compact,
+}
+
+/// Receives warnings, such as fields of outputs that could not be read.
+pub type Warnings = Rc<dyn Fn(&str)>;
+
+/// Fine-tunes how actions are performed.
+#[derive(Clone, Default)]
+pub struct Options {
+  /// Reload Sway once outputs are configured, unless nothing was sent.
+  pub reload_after: bool,
+  /// How long to wait for output changes to settle in watch mode.
+  pub debounce: Duration,
+  /// Record the commands reproducing the layout when saving, to replay
+  /// them verbatim later on.
+  pub record_sequence: bool,
+  /// Read saved layouts back to ensure they were written correctly.
+  pub verify: bool,
+  /// Apply the layout even if it is the one last applied.
+  pub force: bool,
+  /// Position outputs next to each other according to their actual
+  /// width rather than the saved coordinates.
+  pub reanchor: bool,
+  /// Compositor whose output fields should be understood.
+  pub profile: Profile,
+  /// Print a single sd_notify style status line once configured.
+  pub notify: bool,
+  /// Largest width or height accepted from saved layouts.
+  pub max_resolution: Option<u32>,
+  /// Save the layout once an output was changed.
+  pub save: bool,
+  /// Magic string framing messages, for peers not expecting Sway's.
+  pub magic: Option<String>,
+  /// How long to wait for Sway to answer, a few seconds when unset.
+  pub ipc_timeout: Option<Duration>,
+  /// Largest response accepted from Sway, a few megabytes when unset.
+  pub max_payload: Option<usize>,
+  /// How many times to try connecting to Sway, once when unset.
+  pub connect_attempts: u32,
+  /// How long to wait between attempts to connect.
+  pub connect_delay: Duration,
+  /// Refuse to configure layouts with more outputs than this.
+  pub max_outputs: Option<usize>,
+  /// Pause between output commands, for drivers dropping some of them
+  /// when sent too quickly.
+  pub apply_delay: Duration,
+  /// Send output commands one message at a time rather than all at once,
+  /// to tell which one Sway rejected.
+  pub one_by_one: bool,
+  /// OEM identifier of the output positioned at 0,0, the others being
+  /// moved around it.
+  pub origin: Option<String>,
+  /// Storage identifiers of fragments stacked in order over the saved
+  /// layout, each overriding the previous ones.
+  pub fragments: Vec<String>,
+  /// Fail rather than configure a subset when a monitor the saved layout
+  /// expects is not connected.
+  pub strict: bool,
+  /// Compare the target with the live outputs and only send commands for
+  /// those that differ, regardless of what was last applied. Always on in
+  /// watch mode.
+  pub apply_if_changed: bool,
+  /// Record the settings of input devices when saving, restoring them
+  /// along with the layout.
+  pub inputs: bool,
+  /// How layouts and configuration are written to the store.
+  pub store_format: StoreFormat,
+  /// Which parts of the OEM identifiers key saved layouts.
+  pub fingerprint: FingerprintMode,
+  /// Called with what was ignored or recovered from along the way, the
+  /// library printing nothing itself.
+  pub on_warning: Option<Warnings>,
 }
 
-/// Runs the program by executing the requested action.
-pub fn run(socket_path: String, fs_root: String, action: Action) -> Result<String, Error> {
-  connect_to_sway(socket_path).and_then(move |mut ipc| {
-    match (
-      Repository::new(fs_root),
-      request_active_layout(&mut ipc),
-      action,
+/// Commands reaching a layout, planned but not sent yet, so that they can
+/// be shown before being committed or discarded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Staged(Vec<String>);
+
+impl Staged {
+  /// The exact commands committing would send, in order.
+  pub fn commands(&self) -> &[String] {
+    &self.0
+  }
+}
+
+/// Runs the program by executing the requested action against every
+/// given Sway instance. Signals are only caught while watching, so that
+/// other actions can still be interrupted.
+pub fn run(
+  socket_paths: Vec<String>,
+  fs_root: String,
+  action: Action,
+  options: Options,
+) -> Result<String, Error> {
+  let settings = options.clone();
+  let shutdown = match action {
+    Action::Watch => shutdown_on_signals()?,
+    _ => Arc::new(AtomicBool::new(false)),
+  };
+  run_on_each(
+    socket_paths,
+    |path| connect(path, &settings),
+    fs_root,
+    action,
+    options,
+    &shutdown,
+  )
+}
+
+/// Connects to a Sway instance with the magic string, timeout, payload
+/// limit and retries given in the options.
+fn connect(path: String, options: &Options) -> Result<Ipc, io::Error> {
+  let magic = options.magic.as_deref().unwrap_or(ipc::MAGIC_STRING);
+  let timeout = options.ipc_timeout.unwrap_or(ipc::READ_TIMEOUT);
+  let max_payload = options.max_payload.unwrap_or(ipc::MAX_PAYLOAD);
+  let (attempts, delay) = (options.connect_attempts.max(1), options.connect_delay);
+  ipc::connect_or_discover(&path, attempts, delay, &clock::System)
+    .and_then(|stream| Ipc::with_timeout(stream, magic, timeout))
+    .map(|ipc| ipc.with_max_payload(max_payload))
+}
+
+/// Checks the IPC framing end to end against an internal peer answering
+/// with canned outputs, without needing a running Sway.
+pub fn self_test() -> Result<String, Error> {
+  ipc::loopback(Message::GetOutputs.to_bytes(), SELF_TEST_OUTPUTS.to_vec())
+    .map_err(Error::Ipc)
+    .and_then(|reply| parse_layout(&reply, &Options::default()))
+    .map(|layout| format!("self-test passed: {} parsed", outputs(layout.len())))
+}
+
+/// Computes the fingerprint layouts are saved under from outputs in JSON,
+/// as Sway reports them or as saved, without connecting to it.
+pub fn fingerprint_of(json: &str) -> Result<String, serde_json::Error> {
+  serde_json::from_str::<Stored>(json).map(|stored| Saved::from(stored).layout.fingerprint())
+}
+
+/// Returns the layout `auto` would configure on a Sway instance, without
+/// applying or persisting anything.
+pub fn peek(socket_path: String, fs_root: String, options: Options) -> Result<Layout, Error> {
+  connect(socket_path, &options)
+    .map_err(Error::Ipc)
+    .and_then(|ipc| {
+      peek_target(
+        ipc,
+        &Repository::new(fs_root).in_format(options.store_format),
+        &options,
+      )
+    })
+}
+
+/// Plans the commands configuring a layout, such as one returned by peek,
+/// without talking to Sway.
+pub fn stage(layout: &Layout) -> Staged {
+  Staged(layout.serialize_commands())
+}
+
+/// Sends staged commands to a Sway instance, stopping at the first one
+/// that fails.
+pub fn commit(staged: Staged, socket_path: String, options: Options) -> Result<(), Error> {
+  connect(socket_path, &options)
+    .map_err(Error::Ipc)
+    .and_then(|ipc| commit_on(ipc, staged))
+}
+
+/// Drops staged commands without sending them.
+pub fn discard(staged: Staged) {
+  drop(staged)
+}
+
+/// Sends staged commands over a given transport.
+fn commit_on<T>(mut ipc: T, staged: Staged) -> Result<(), Error>
+where
+  T: Transport,
+{
+  staged
+    .0
+    .into_iter()
+    .try_for_each(|command| run_output_command((&mut ipc, Message::RunCommand(command))))
+}
+
+/// Configures a Sway instance like `auto` would, only sending commands for
+/// outputs whose settings differ from the target. Returns the commands
+/// actually sent, none if the layout is already in place.
+pub fn apply_if_changed(
+  socket_path: String,
+  fs_root: String,
+  options: Options,
+) -> Result<Vec<String>, Error> {
+  connect(socket_path.clone(), &options)
+    .map_err(Error::Ipc)
+    .and_then(|ipc| {
+      apply_changes(
+        ipc,
+        &socket_path,
+        &Repository::new(fs_root).in_format(options.store_format),
+        &options,
+      )
+    })
+}
+
+/// Detects the active layout and sends what differs from its target.
+fn apply_changes<T>(
+  mut ipc: T,
+  socket: &str,
+  repo: &Repository,
+  options: &Options,
+) -> Result<Vec<String>, Error>
+where
+  T: Transport,
+{
+  request_active_layout(&mut ipc, options)
+    .and_then(|layout| apply_delta(repo, ipc, socket, layout, None, options, &clock::System))
+}
+
+/// Detects the active layout and plans the target it would be merged to.
+fn peek_target(
+  mut ipc: impl Transport,
+  repo: &Repository,
+  options: &Options,
+) -> Result<Layout, Error> {
+  request_active_layout(&mut ipc, options)
+    .and_then(|layout| plan(repo, layout, options))
+    .map(|(target, _)| target)
+}
+
+/// Performs the action on each instance in turn. A failing instance does
+/// not prevent the others from being attempted. With several instances,
+/// each line printed is prefixed with the socket it comes from.
+/// Watching blocks, so only a single instance can be watched.
+fn run_on_each<T, F>(
+  socket_paths: Vec<String>,
+  connect: F,
+  fs_root: String,
+  action: Action,
+  options: Options,
+  shutdown: &AtomicBool,
+) -> Result<String, Error>
+where
+  T: Transport + Events,
+  F: Fn(String) -> Result<T, io::Error>,
+{
+  let repo = Repository::new(fs_root.clone()).in_format(options.store_format);
+  if let Some(result) = run_offline(repo, &action, &options) {
+    return result;
+  }
+  let count = socket_paths.len();
+  if matches!(action, Action::Watch) && count > 1 {
+    return Err(Error::WatchMany(count));
+  }
+  let mut outputs = Vec::<String>::with_capacity(count);
+  let mut failures = Vec::<(String, Error)>::new();
+  for path in socket_paths {
+    match run_on_socket(
+      &connect,
+      path.clone(),
+      Repository::new(fs_root.clone()).in_format(options.store_format),
+      action.clone(),
+      &options,
+      shutdown,
     ) {
-      (repo, Ok(layout), Action::Auto) => silently_configure_layout(repo, ipc, layout),
-      (repo, Ok(layout), Action::Save) => silently_save_layout(repo, layout),
-      (_, Ok(layout), _) => Ok(layout.to_string()),
-      (_, Err(error), _) => Err(error),
+      Ok(ref output) if output.is_empty() => (),
+      Ok(output) if count == 1 => outputs.push(output),
+      Ok(output) => outputs.extend(output.lines().map(|line| format!("{}: {}", path, line))),
+      Err(error) => failures.push((path, error)),
     }
-  })
+  }
+  match (failures.len(), count) {
+    (0, _) => Ok(outputs.join("\n")),
+    (_, 1) => Err(failures.remove(0).1),
+    _ => Err(Error::Instances(failures)),
+  }
 }
 
-/// Returns a handy IPC instance.
-fn connect_to_sway(socket_path: String) -> Result<Ipc, Error> {
-  Ipc::connect(socket_path).map_err(Error::Ipc)
+/// Performs the actions only involving the store, once and without
+/// connecting to Sway. Returns nothing for the other actions.
+fn run_offline(
+  repo: Repository,
+  action: &Action,
+  options: &Options,
+) -> Option<Result<String, Error>> {
+  match *action {
+    Action::Confirm => Some(
+      repo
+        .save(String::from(CONFIRMED), true)
+        .map(|_| String::new())
+        .map_err(Error::Save),
+    ),
+    Action::Saved(since) => Some(list_saved_layouts(repo, since, clock::System.now())),
+    Action::Normalize => Some(normalize_store(repo)),
+    Action::Info(ref id) => Some(describe_saved(&repo, id.clone())),
+    Action::Show(ref id) => Some(show_saved(&repo, id.clone())),
+    Action::Import(ref path) => Some(import_layout(&repo, path, options)),
+    Action::Export(ref id, ref path) => Some(export_layout(&repo, id.clone(), path)),
+    _ => None,
+  }
+}
+
+/// Connects to a single Sway instance to perform the action. Watching
+/// requires a second connection dedicated to events.
+fn run_on_socket<T, F>(
+  connect: &F,
+  path: String,
+  repo: Repository,
+  action: Action,
+  options: &Options,
+  shutdown: &AtomicBool,
+) -> Result<String, Error>
+where
+  T: Transport + Events,
+  F: Fn(String) -> Result<T, io::Error>,
+{
+  let ipc = connect(path.clone()).map_err(Error::Ipc)?;
+  match action {
+    Action::Watch => connect(path.clone())
+      .map_err(Error::Ipc)
+      .and_then(|events| watch_outputs(ipc, events, &path, repo, options, shutdown)),
+    _ => run_once(ipc, &path, repo, action, options),
+  }
+}
+
+/// What was found on a Sway instance, requested once so that configuring
+/// it works on the same snapshot instead of querying again.
+struct Detection {
+  /// The active outputs.
+  layout: Layout,
+  /// The workspaces.
+  workspaces: Vec<Workspace>,
+  /// The tree of containers, only requested when nothing is saved for
+  /// the layout.
+  tree: Option<serde_json::Value>,
+}
+
+impl Detection {
+  /// Fetches everything configuring the layout may need.
+  fn request(
+    ipc: &mut impl Transport,
+    repo: &Repository,
+    options: &Options,
+  ) -> Result<Self, Error> {
+    request_active_layout(ipc, options)
+      .and_then(|layout| Self::complete(ipc, repo, layout, options))
+  }
+
+  /// Fetches what configuring a layout already detected may need.
+  fn complete(
+    ipc: &mut impl Transport,
+    repo: &Repository,
+    layout: Layout,
+    options: &Options,
+  ) -> Result<Self, Error> {
+    let workspaces = request_workspaces(ipc)?;
+    let tree = match repo.contains(layout.fingerprint_with(options.fingerprint)) {
+      true => None,
+      false => Some(request_tree(ipc)?),
+    };
+    Ok(Detection {
+      layout,
+      workspaces,
+      tree,
+    })
+  }
+}
+
+/// Executes the requested action against a single Sway instance.
+fn run_once<T>(
+  mut ipc: T,
+  socket: &str,
+  repo: Repository,
+  action: Action,
+  options: &Options,
+) -> Result<String, Error>
+where
+  T: Transport,
+{
+  match (repo, request_active_layout(&mut ipc, options), action) {
+    (repo, Ok(layout), Action::Auto) => Detection::complete(&mut ipc, &repo, layout, options)
+      .and_then(|detection| silently_configure_layout(repo, ipc, socket, detection, options)),
+    (repo, Ok(layout), Action::Try(timeout)) => {
+      let sentinel = Sentinel::new(repo.path(String::from(CONFIRMED)), clock::System);
+      try_layout(repo, ipc, socket, layout, options, sentinel, timeout)
+    }
+    (repo, Ok(layout), Action::Transform(name, transform)) => {
+      transform_output(repo, ipc, layout, &name, &transform, options)
+    }
+    (repo, Ok(layout), Action::Disable(name)) => {
+      toggle_output(&repo, ipc, layout, &name, false, options)
+    }
+    (repo, Ok(layout), Action::Enable(name)) => {
+      toggle_output(&repo, ipc, layout, &name, true, options)
+    }
+    (repo, Ok(layout), Action::Save(name)) => {
+      let workspaces = request_workspaces(&mut ipc)?;
+      let inputs = match options.inputs {
+        true => request_inputs(&mut ipc)?,
+        false => Vec::new(),
+      };
+      silently_save_layout(repo, layout, &workspaces, &inputs, name, options)
+    }
+    (repo, Ok(layout), Action::Apply(name)) => {
+      apply_named(repo, ipc, socket, layout, &name, options)
+    }
+    (repo, Ok(layout), Action::Delete) => repo
+      .delete(layout.fingerprint_with(options.fingerprint))
+      .map(|_| String::new())
+      .map_err(Error::Delete),
+    (repo, Ok(layout), Action::Rank) => rank_saved_layouts(repo, layout),
+    (repo, Ok(layout), Action::Diff) => diff_saved(&repo, layout, options),
+    (repo, Ok(layout), Action::DryRun) => {
+      plan(&repo, layout, options).map(|(_, commands)| commands.join("\n"))
+    }
+    (_, Ok(layout), Action::Defaults) => Ok(render_diff(layout.diff(&layout.defaults()))),
+    (_, Ok(layout), Action::List(Format::Env)) => Ok(layout.to_env()),
+    (_, Ok(layout), Action::List(Format::Json(style))) => layout
+      .to_json(matches!(style, JsonStyle::Pretty))
+      .map_err(Error::Print),
+    (_, Ok(layout), Action::Map) => Ok(layout.to_map()),
+    (_, Ok(layout), Action::Fingerprint) => Ok(layout.fingerprint_with(options.fingerprint)),
+    (_, Ok(_), Action::Workspaces) => {
+      request_workspaces(&mut ipc).map(|workspaces| workspace::by_output(&workspaces))
+    }
+    (_, Ok(layout), _) => Ok(layout.to_string()),
+    (_, Err(error), _) => Err(error),
+  }
+}
+
+/// Configure layout, then again each time outputs change until the
+/// connection to Sway is closed.
+fn watch_outputs<T>(
+  mut ipc: T,
+  mut events: T,
+  socket: &str,
+  repo: Repository,
+  options: &Options,
+  shutdown: &AtomicBool,
+) -> Result<String, Error>
+where
+  T: Transport + Events,
+{
+  let options = &Options {
+    apply_if_changed: true,
+    reload_after: false,
+    ..options.clone()
+  };
+  let mut last_layout = None;
+  let mut apply = || match Detection::request(&mut ipc, &repo, options).and_then(|detection| {
+    let fingerprint = detection.layout.fingerprint_with(options.fingerprint);
+    configure(&repo, &mut ipc, socket, detection, options).map(|_| fingerprint)
+  }) {
+    Ok(fingerprint) => last_layout = Some(fingerprint),
+    Err(error) => warn(options, &error.to_string()),
+  };
+  subscribe_to_outputs(&mut events)?;
+  apply();
+  let stopped = watch::watch(
+    &mut events,
+    options.debounce,
+    shutdown,
+    &clock::System,
+    &mut apply,
+  );
+  let mut states: HashMap<String, watch::State> =
+    repo.load(String::from(WATCH_STATE)).unwrap_or_default();
+  states.insert(
+    String::from(socket),
+    watch::State {
+      last_layout,
+      stopped,
+    },
+  );
+  repo
+    .save(String::from(WATCH_STATE), states)
+    .map(|_| String::new())
+    .map_err(Error::Save)
+}
+
+/// Returns a flag raised once SIGINT or SIGTERM is received.
+fn shutdown_on_signals() -> Result<Arc<AtomicBool>, Error> {
+  let flag = Arc::new(AtomicBool::new(false));
+  for signal in &[signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+    signal_hook::flag::register(*signal, flag.clone()).map_err(Error::Signal)?;
+  }
+  Ok(flag)
+}
+
+/// Ask Sway to report output changes.
+fn subscribe_to_outputs(events: &mut impl Transport) -> Result<(), Error> {
+  let message = Message::Subscribe(vec![String::from("output")]);
+  match events
+    .roundtrip(message.to_bytes())
+    .map_err(Error::Ipc)
+    .map(Response::scan)?
+  {
+    true => Ok(()),
+    false => Err(Error::Configuration(message, None)),
+  }
 }
 
 /// Ask Sway what the current layout is.
-fn request_active_layout(ipc: &mut Ipc) -> Result<Layout, Error> {
+fn request_active_layout(ipc: &mut impl Transport, options: &Options) -> Result<Layout, Error> {
   ipc
     .roundtrip(Message::GetOutputs.to_bytes())
     .map_err(Error::Ipc)
-    .map(|data| serde_json::from_slice(&data))?
-    .map_err(Error::ActiveLayout)
+    .and_then(|data| parse_layout(&data, options))
+    .map(Layout::dedup_names)
+}
+
+/// Ask Sway which workspaces exist and where.
+fn request_workspaces(ipc: &mut impl Transport) -> Result<Vec<Workspace>, Error> {
+  ipc
+    .roundtrip(Message::GetWorkspaces.to_bytes())
+    .map_err(Error::Ipc)
+    .and_then(|data| serde_json::from_slice(&data).map_err(Error::Response))
+}
+
+/// Ask Sway for its tree of containers, telling which output has focus.
+fn request_tree(ipc: &mut impl Transport) -> Result<serde_json::Value, Error> {
+  ipc
+    .roundtrip(Message::GetTree.to_bytes())
+    .map_err(Error::Ipc)
+    .and_then(|data| serde_json::from_slice(&data).map_err(Error::Response))
+}
+
+/// Ask Sway which input devices are connected and how they are set up.
+fn request_inputs(ipc: &mut impl Transport) -> Result<Vec<Input>, Error> {
+  ipc
+    .roundtrip(Message::GetInputs.to_bytes())
+    .map_err(Error::Ipc)
+    .and_then(|data| input::parse(&data).map_err(Error::Response))
+}
+
+/// Parses outputs strictly, falling back to a best-effort layout so that
+/// unexpected values reported by newer Sway versions don't prevent
+/// configuration. Fields are renamed first according to the compositor.
+fn parse_layout(data: &[u8], options: &Options) -> Result<Layout, Error> {
+  let value = serde_json::from_slice(data)
+    .map(|v| layout::lift_reported_settings(options.profile.normalize(v)))
+    .map_err(Error::ActiveLayout)?;
+  serde_json::from_value(value.clone()).or_else(|err| {
+    let (layout, dropped) = Layout::lenient(value).map_err(|_| Error::ActiveLayout(err))?;
+    for what in dropped {
+      warn(options, &format!("ignored unparsable {}", what));
+    }
+    Ok(layout)
+  })
+}
+
+/// Hands a warning to the caller, if it listens to them.
+fn warn(options: &Options, message: &str) {
+  if let Some(ref on_warning) = options.on_warning {
+    on_warning(message)
+  }
 }
 
 /// Persist layout without producing stdout content.
-fn silently_save_layout(repo: Repository, layout: Layout) -> Result<String, Error> {
+fn silently_save_layout(
+  repo: Repository,
+  layout: Layout,
+  workspaces: &[Workspace],
+  inputs: &[Input],
+  name: Option<String>,
+  options: &Options,
+) -> Result<String, Error> {
+  let saved = Saved {
+    command_sequence: match options.record_sequence {
+      true => Some(layout.serialize_commands()),
+      false => None,
+    },
+    workspaces: match workspaces.is_empty() {
+      true => None,
+      false => Some(workspace::assignments(workspaces, &layout.connectors())),
+    },
+    inputs: match inputs.is_empty() {
+      true => None,
+      false => Some(inputs.to_vec()),
+    },
+    ..Saved::new(layout).stamped(clock::System.now())
+  };
+  let id = saved.layout.fingerprint_with(options.fingerprint);
+  if let Some(name) = name {
+    let saved = Saved {
+      fingerprint: Some(id),
+      ..saved
+    };
+    return repo
+      .save_named(&name, &saved)
+      .and_then(|_| match options.verify {
+        true => repo.verify_named(&name, &saved),
+        false => Ok(()),
+      })
+      .map_err(Error::Save)
+      .map(|_| String::new());
+  }
   repo
-    .save(layout.fingerprint(), &layout)
+    .save(id.clone(), &saved)
+    .and_then(|_| match options.verify {
+      true => repo.verify(id, &saved),
+      false => Ok(()),
+    })
     .map_err(Error::Save)
     .map(|_| String::new())
 }
 
+/// Configures the layout saved under a name over the current one,
+/// whatever its fingerprint, like auto would the one saved for it. Fails
+/// if none of its outputs are connected.
+fn apply_named<T>(
+  repo: Repository,
+  ipc: T,
+  socket: &str,
+  layout: Layout,
+  name: &str,
+  options: &Options,
+) -> Result<String, Error>
+where
+  T: Transport,
+{
+  let saved = repo
+    .load_named::<Stored>(name)
+    .map(|stored| Saved::from(stored).upgraded())
+    .map_err(Error::Load)?;
+  let max = options.max_resolution.unwrap_or(MAX_RESOLUTION);
+  saved
+    .layout
+    .check_resolutions(max)
+    .map_err(|reason| Error::Load(StorageError::Invalid(String::from(name), reason)))?;
+  apply_layout(
+    &repo,
+    ipc,
+    socket,
+    layout,
+    Some(saved),
+    options,
+    &clock::System,
+  )
+  .map(|_| String::new())
+}
+
+/// Rewrites every stored file in its canonical form, reporting the ones
+/// that changed and skipping those that can't be read.
+fn normalize_store(repo: Repository) -> Result<String, Error> {
+  let report = repo
+    .ids()
+    .map_err(Error::Load)?
+    .into_iter()
+    .filter_map(|id| match normalize_file(&repo, id.clone()) {
+      Ok(true) => Some(format!("{}: rewritten", id)),
+      Ok(false) => None,
+      Err(err) => Some(format!("{}: skipped: {}", id, err)),
+    })
+    .collect::<Vec<String>>();
+  Ok(report.join("\n"))
+}
+
+/// Upgrades a saved layout to the current schema, or merely reformats
+/// any other file such as configuration. Only files named after a
+/// fingerprint or holding a layout key are taken for layouts, as any
+/// array would pass for a bare one.
+fn normalize_file(repo: &Repository, id: String) -> Result<bool, StorageError> {
+  let value = repo.load::<serde_json::Value>(id.clone())?;
+  if !is_fingerprint(&id) && value.get("layout").is_none() {
+    return repo.rewrite(id, value);
+  }
+  match serde_json::from_value::<Stored>(value.clone()) {
+    Ok(stored) => repo.rewrite(id, Saved::from(stored).upgraded()),
+    Err(_) => repo.rewrite(id, value),
+  }
+}
+
+/// True if an identifier looks like a layout fingerprint.
+fn is_fingerprint(id: &str) -> bool {
+  id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Describe how each saved layout matches the current one, best first.
+fn rank_saved_layouts(repo: Repository, layout: Layout) -> Result<String, Error> {
+  repo
+    .list()
+    .map_err(Error::Load)
+    .map(saved_layouts)
+    .map(|saved| layout.rank(saved))
+    .map(|ranking| {
+      ranking
+        .iter()
+        .map(|(id, score)| format!("{} {}", id, score))
+        .collect::<Vec<String>>()
+        .join("\n")
+    })
+}
+
+/// Print the identifier of each saved layout along with its number of
+/// outputs.
+fn list_saved_layouts(
+  repo: Repository,
+  since: Option<Duration>,
+  now: SystemTime,
+) -> Result<String, Error> {
+  let threshold = since
+    .and_then(|d| now.checked_sub(d))
+    .unwrap_or(SystemTime::UNIX_EPOCH);
+  repo
+    .list_since::<Stored>(threshold)
+    .map_err(Error::Load)
+    .map(|mut listing| {
+      listing
+        .drain(..)
+        .map(|(id, stored)| (id, Saved::from(stored)))
+        .map(|(id, saved)| match saved.provenance() {
+          Some(provenance) => format!("{} {} {}", id, outputs(saved.layout.len()), provenance),
+          None => format!("{} {}", id, outputs(saved.layout.len())),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+    })
+}
+
+/// Prints the metadata of a saved layout, one field per line.
+fn describe_saved(repo: &Repository, id: String) -> Result<String, Error> {
+  let saved = Saved::from(repo.load::<Stored>(id).map_err(Error::Load)?);
+  let unknown = || String::from("unknown");
+  Ok(format!(
+    "version: {}\nsaved at: {}\nhostname: {}\noutputs: {}",
+    saved.version.map(|v| v.to_string()).unwrap_or_else(unknown),
+    saved.saved_at.unwrap_or_else(unknown),
+    saved.hostname.unwrap_or_else(unknown),
+    saved.layout.len()
+  ))
+}
+
+/// Prints the commands of a saved layout, preceded by a comment telling
+/// when and where it was saved if known.
+fn show_saved(repo: &Repository, id: String) -> Result<String, Error> {
+  let saved = Saved::from(repo.load::<Stored>(id).map_err(Error::Load)?);
+  let commands = saved.layout.serialize_commands();
+  let lines = saved
+    .provenance()
+    .map(|provenance| format!("# {}", provenance));
+  Ok(
+    lines
+      .into_iter()
+      .chain(commands)
+      .collect::<Vec<String>>()
+      .join("\n"),
+  )
+}
+
+/// Saves the layout of a JSON file, bare or wrapped, under its
+/// fingerprint, which is returned.
+fn import_layout(repo: &Repository, path: &str, options: &Options) -> Result<String, Error> {
+  let data = fs::read(path)
+    .map_err(StorageError::Io)
+    .map_err(Error::Load)?;
+  let invalid = |reason: String| Error::Load(StorageError::Invalid(String::from(path), reason));
+  let saved = serde_json::from_slice::<Stored>(&data)
+    .map(|stored| Saved::from(stored).upgraded())
+    .map_err(|err| invalid(err.to_string()))?;
+  if saved.layout.is_empty() {
+    return Err(invalid(String::from("no output to save")));
+  }
+  let fingerprint = saved.layout.fingerprint_with(options.fingerprint);
+  repo
+    .save(fingerprint.clone(), saved)
+    .map(|_| fingerprint)
+    .map_err(Error::Save)
+}
+
+/// Writes the layout saved under a fingerprint to a JSON file, whatever
+/// the format of the store.
+fn export_layout(repo: &Repository, id: String, path: &str) -> Result<String, Error> {
+  let saved = Saved::from(repo.load::<Stored>(id).map_err(Error::Load)?);
+  serde_json::to_string_pretty(&saved)
+    .map_err(StorageError::Json)
+    .and_then(|data| fs::write(path, data).map_err(StorageError::Io))
+    .map(|_| String::new())
+    .map_err(Error::Save)
+}
+
+/// Unwraps listed layouts.
+fn saved_layouts(mut listing: Vec<(String, Stored)>) -> Vec<(String, Layout)> {
+  listing
+    .drain(..)
+    .map(|(id, stored)| (id, Saved::from(stored).layout))
+    .collect()
+}
+
+/// Compares the current layout with the one saved for it, if any.
+fn diff_saved(repo: &Repository, layout: Layout, options: &Options) -> Result<String, Error> {
+  let max = options.max_resolution.unwrap_or(MAX_RESOLUTION);
+  match load_saved(repo, &layout, max, options.fingerprint)? {
+    Some(saved) => Ok(render_diff(layout.diff(&saved.layout))),
+    None => Ok(String::from("no saved layout for this fingerprint")),
+  }
+}
+
+/// One line per output that differs.
+fn render_diff(diff: Vec<OutputDiff>) -> String {
+  diff
+    .iter()
+    .map(OutputDiff::to_string)
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
 /// Apply configuration without producing stdout content.
-fn silently_configure_layout(repo: Repository, ipc: Ipc, layout: Layout) -> Result<String, Error> {
-  apply_configuration(repo, ipc, layout).map(|_| String::new())
+fn silently_configure_layout<T>(
+  repo: Repository,
+  ipc: T,
+  socket: &str,
+  detection: Detection,
+  options: &Options,
+) -> Result<String, Error>
+where
+  T: Transport,
+{
+  let status = match options.notify {
+    true => Some(status_line(&detection.layout, options.fingerprint)),
+    false => None,
+  };
+  let applied = configure(&repo, ipc, socket, detection, options)?;
+  match status {
+    Some(status) if applied => Ok(format!("STATUS=applied {}", status)),
+    Some(status) => Ok(format!("STATUS=unchanged {}", status)),
+    None => Ok(String::new()),
+  }
+}
+
+/// Applies the layout detected, then moves workspaces and sets input
+/// devices up as saved with it, bringing focus back to the workspace that
+/// had it. With nothing saved for the layout, the output holding focus is
+/// enabled. Returns whether anything was sent.
+fn configure<T>(
+  repo: &Repository,
+  mut ipc: T,
+  socket: &str,
+  detection: Detection,
+  options: &Options,
+) -> Result<bool, Error>
+where
+  T: Transport,
+{
+  let Detection {
+    layout,
+    workspaces,
+    tree,
+  } = detection;
+  let layout = match tree {
+    Some(tree) => layout.activate_focused(&tree),
+    None => layout,
+  };
+  let max = options.max_resolution.unwrap_or(MAX_RESOLUTION);
+  let saved = load_saved(repo, &layout, max, options.fingerprint)?;
+  let applied = apply_layout(
+    repo,
+    &mut ipc,
+    socket,
+    layout.clone(),
+    saved.clone(),
+    options,
+    &clock::System,
+  )?;
+  if options.reload_after && applied {
+    run_output_command((&mut ipc, Message::RunCommand(String::from("reload"))))?;
+  }
+  if let Some(saved) = saved.filter(|_| applied) {
+    for command in restore_commands(&saved, &layout, &workspaces) {
+      run_output_command((&mut ipc, Message::RunCommand(command)))?;
+    }
+  }
+  if let Some(name) = workspace::focused(&workspaces).filter(|_| applied) {
+    run_output_command((ipc, Message::RunCommand(workspace::focus_command(&name))))?;
+  }
+  Ok(applied)
+}
+
+/// Commands moving the workspaces that exist back to the monitors they
+/// were saved on, then restoring the input devices saved along. Sway keeps
+/// the settings of devices not connected yet.
+fn restore_commands(saved: &Saved, layout: &Layout, workspaces: &[Workspace]) -> Vec<String> {
+  let moves = match saved.workspaces {
+    Some(ref assigned) => {
+      workspace::assignment_commands(assigned, workspaces, &layout.connectors())
+    }
+    None => Vec::new(),
+  };
+  let inputs = saved.inputs.iter().flatten().flat_map(Input::commands);
+  moves.into_iter().chain(inputs).collect()
+}
+
+/// Summarizes which layout is configured, for service managers.
+fn status_line(layout: &Layout, mode: FingerprintMode) -> String {
+  format!(
+    "layout {} ({})",
+    layout.fingerprint_with(mode),
+    outputs(layout.len())
+  )
+}
+
+/// Counts outputs in words, such as `1 output` or `2 outputs`.
+fn outputs(count: usize) -> String {
+  match count {
+    1 => String::from("1 output"),
+    _ => format!("{} outputs", count),
+  }
+}
+
+/// Translate layout to a set of declarative commands and execute them,
+/// unless they are exactly the ones last applied to this instance.
+/// Returns whether anything was sent.
+fn apply_configuration<T>(
+  repo: &Repository,
+  ipc: T,
+  socket: &str,
+  layout: Layout,
+  options: &Options,
+  clock: &impl Clock,
+) -> Result<bool, Error>
+where
+  T: Transport,
+{
+  apply_layout(repo, ipc, socket, layout, None, options, clock)
+}
+
+/// Same as apply_configuration, merging the given saved layout rather
+/// than the one saved for the current layout, if any.
+fn apply_layout<T>(
+  repo: &Repository,
+  ipc: T,
+  socket: &str,
+  layout: Layout,
+  named: Option<Saved>,
+  options: &Options,
+  clock: &impl Clock,
+) -> Result<bool, Error>
+where
+  T: Transport,
+{
+  match options.max_outputs {
+    Some(max) if layout.len() > max => return Err(Error::TooManyOutputs(layout.len(), max)),
+    _ => (),
+  }
+  if options.apply_if_changed && !options.force {
+    return apply_delta(repo, ipc, socket, layout, named, options, clock)
+      .map(|sent| !sent.is_empty());
+  }
+  let fast = named.is_none() && takes_fast_path(repo, &layout, options);
+  if fast && !repo.exists() {
+    return commit_on(ipc, stage(&layout)).map(|_| true);
+  }
+  let (target, commands) = match fast {
+    true => (layout.clone(), layout.serialize_commands()),
+    false => plan_from(repo, layout, named, options)?,
+  };
+  target.validate().map_err(Error::InvalidLayout)?;
+  let digest = digest_of(&commands);
+  let mut applied: HashMap<String, String> =
+    repo.load(String::from(LAST_APPLIED)).unwrap_or_default();
+  if !options.force && applied.get(socket) == Some(&digest) {
+    return Ok(false);
+  }
+  send_commands(ipc, commands, options, clock)?;
+  applied.insert(String::from(socket), digest);
+  repo
+    .save(String::from(LAST_APPLIED), applied)
+    .map(|_| true)
+    .map_err(Error::Save)
+}
+
+/// Sends only the commands of outputs configured differently from the
+/// target, returning them. A recorded sequence can't be split and is
+/// replayed whole as soon as anything differs. Once sent, the planned
+/// commands are recorded as the last applied ones.
+fn apply_delta<T>(
+  repo: &Repository,
+  ipc: T,
+  socket: &str,
+  layout: Layout,
+  named: Option<Saved>,
+  options: &Options,
+  clock: &impl Clock,
+) -> Result<Vec<String>, Error>
+where
+  T: Transport,
+{
+  let (target, commands) = plan_from(repo, layout.clone(), named, options)?;
+  target.validate().map_err(Error::InvalidLayout)?;
+  let changed = layout.delta(&target);
+  let digest = digest_of(&commands);
+  let sent = match changed.is_empty() || commands == target.serialize_commands() {
+    true => changed,
+    false => commands,
+  };
+  if sent.is_empty() {
+    return Ok(sent);
+  }
+  send_commands(ipc, sent.clone(), options, clock)?;
+  let mut applied: HashMap<String, String> =
+    repo.load(String::from(LAST_APPLIED)).unwrap_or_default();
+  applied.insert(String::from(socket), digest);
+  repo
+    .save(String::from(LAST_APPLIED), applied)
+    .map(|_| sent)
+    .map_err(Error::Save)
+}
+
+/// Sends output commands in order, pausing in between if configured to.
+/// Without a pause, they are joined in a single message unless asked to
+/// send them one by one.
+fn send_commands<T>(
+  mut ipc: T,
+  commands: Vec<String>,
+  options: &Options,
+  clock: &impl Clock,
+) -> Result<(), Error>
+where
+  T: Transport,
+{
+  let paused = options.apply_delay > Duration::from_millis(0);
+  if !options.one_by_one && !paused && !commands.is_empty() {
+    return run_output_command((ipc, Message::RunCommand(commands.join("; "))));
+  }
+  for (i, command) in commands.into_iter().enumerate() {
+    if i > 0 && paused {
+      clock.sleep(options.apply_delay);
+    }
+    run_output_command((&mut ipc, Message::RunCommand(command)))?;
+  }
+  Ok(())
+}
+
+/// True for a lone output with no layout saved for it, the common case
+/// of a laptop at login: without any saved layout nor option or
+/// configuration moving it, enabling the output as is sends the same
+/// command as the general path, without loading nor merging anything.
+/// Only what was last applied is still recorded, if there is a store.
+fn takes_fast_path(repo: &Repository, layout: &Layout, options: &Options) -> bool {
+  let configured = |id: &str| repo.contains(String::from(id));
+  layout.len() == 1
+    && !options.reanchor
+    && !options.strict
+    && options.origin.is_none()
+    && options.fragments.is_empty()
+    && !repo.contains(layout.fingerprint_with(options.fingerprint))
+    && ![PINS, SCALING, FRAGMENTS].iter().any(|id| configured(id))
+}
+
+/// Rotates one output of the active layout, saving the result if asked to.
+fn transform_output<T>(
+  repo: Repository,
+  ipc: T,
+  layout: Layout,
+  name: &str,
+  transform: &str,
+  options: &Options,
+) -> Result<String, Error>
+where
+  T: Transport,
+{
+  let layout = layout
+    .set_transform(name, transform)
+    .map_err(Error::Output)?;
+  let command = layout
+    .command_for(name)
+    .ok_or_else(|| Error::Output(format!("unknown output: {}", name)))?;
+  run_output_command((ipc, Message::RunCommand(command)))?;
+  match options.save {
+    true => save_transform(repo, layout, name, transform, options),
+    false => Ok(String::new()),
+  }
+}
+
+/// Records a new transform in the layout saved for the current one,
+/// keeping everything else saved with it. With nothing saved yet, the
+/// current layout is saved instead.
+fn save_transform(
+  repo: Repository,
+  layout: Layout,
+  name: &str,
+  transform: &str,
+  options: &Options,
+) -> Result<String, Error> {
+  let max = options.max_resolution.unwrap_or(MAX_RESOLUTION);
+  let saved = match load_saved(&repo, &layout, max, options.fingerprint)? {
+    Some(saved) => saved,
+    None => return silently_save_layout(repo, layout, &[], &[], None, options),
+  };
+  let connector = layout
+    .connectors()
+    .into_iter()
+    .find(|(_, connector)| connector == name)
+    .and_then(|(id, _)| saved.layout.connectors().remove(&id))
+    .unwrap_or_else(|| String::from(name));
+  let saved = Saved {
+    layout: saved
+      .layout
+      .set_transform(&connector, transform)
+      .map_err(Error::Output)?,
+    ..saved
+  };
+  repo
+    .save(layout.fingerprint_with(options.fingerprint), saved)
+    .map(|_| String::new())
+    .map_err(Error::Save)
+}
+
+/// Enables or disables a single output. Enabled outputs get their saved
+/// position, resolution and transform if a layout was saved, the live
+/// ones otherwise.
+fn toggle_output<T>(
+  repo: &Repository,
+  ipc: T,
+  layout: Layout,
+  name: &str,
+  active: bool,
+  options: &Options,
+) -> Result<String, Error>
+where
+  T: Transport,
+{
+  let layout = match active {
+    true => {
+      let max = options.max_resolution.unwrap_or(MAX_RESOLUTION);
+      let saved = load_saved(repo, &layout, max, options.fingerprint)?;
+      merge_or_current(saved, layout, options)?
+    }
+    false => layout,
+  };
+  let command = layout
+    .set_active(name, active)
+    .map_err(Error::Output)?
+    .command_for(name)
+    .ok_or_else(|| Error::Output(format!("unknown output: {}", name)))?;
+  run_output_command((ipc, Message::RunCommand(command))).map(|_| String::new())
 }
 
-/// Translate layout to a set of declarative commands and execute them.
-fn apply_configuration(repo: Repository, ipc: Ipc, layout: Layout) -> Result<(), Error> {
-  merge_or_current(repo, layout)
+/// Applies the layout then waits for the user to confirm it, restoring
+/// the layout found beforehand otherwise.
+fn try_layout<T, C>(
+  repo: Repository,
+  mut ipc: T,
+  socket: &str,
+  layout: Layout,
+  options: &Options,
+  mut confirmation: C,
+  timeout: Duration,
+) -> Result<String, Error>
+where
+  T: Transport,
+  C: Confirmation,
+{
+  let previous = layout.clone();
+  let forced = Options {
+    force: true,
+    ..options.clone()
+  };
+  apply_configuration(&repo, &mut ipc, socket, layout, &forced, &clock::System)?;
+  if confirmation.wait(timeout) {
+    return Ok(String::new());
+  }
+  previous
     .serialize_commands()
     .drain(..)
     .map(Message::RunCommand)
-    .map(|m| (ipc.clone(), m))
-    .map(run_output_command)
-    .collect()
+    .try_for_each(|m| run_output_command((&mut ipc, m)))?;
+  let mut applied: HashMap<String, String> =
+    repo.load(String::from(LAST_APPLIED)).unwrap_or_default();
+  applied.remove(socket);
+  repo
+    .save(String::from(LAST_APPLIED), applied)
+    .map_err(Error::Save)?;
+  Ok(String::from(
+    "not confirmed in time, previous layout restored",
+  ))
+}
+
+/// Returns a string that is unique for a given set of commands.
+fn digest_of(commands: &[String]) -> String {
+  let mut hasher = Sha256::new();
+  hasher.input(commands.join("\n").as_bytes());
+  format!("{:x}", hasher.result())
+}
+
+/// Returns the layout to reach and the commands reaching it: the
+/// recorded command sequence if any, or commands derived from the layout.
+fn plan(
+  repo: &Repository,
+  layout: Layout,
+  options: &Options,
+) -> Result<(Layout, Vec<String>), Error> {
+  plan_from(repo, layout, None, options)
+}
+
+/// Same as plan, starting from the given saved layout rather than the
+/// one saved for the current layout, if any. Unlike the one saved for
+/// it, a given layout that doesn't line up with the current one fails.
+fn plan_from(
+  repo: &Repository,
+  layout: Layout,
+  named: Option<Saved>,
+  options: &Options,
+) -> Result<(Layout, Vec<String>), Error> {
+  let max = options.max_resolution.unwrap_or(MAX_RESOLUTION);
+  let required = named.is_some();
+  let saved = match named {
+    Some(saved) => Some(saved),
+    None => load_saved(repo, &layout, max, options.fingerprint)?,
+  };
+  if options.strict {
+    check_expected(repo, saved.as_ref(), &layout)?;
+  }
+  let sequence = saved.as_ref().and_then(|s| s.command_sequence.clone());
+  let live = layout.apply_scaling(&load_scaling(repo));
+  let current = match saved {
+    Some(saved) if required => live
+      .merge_with(
+        saved.layout,
+        saved.merge_policy.unwrap_or_default(),
+        options.fingerprint,
+      )
+      .map_err(Error::Merge)?,
+    saved => merge_or_current(saved, live, options)?,
+  };
+  let merged = load_fragments(repo, options)?
+    .iter()
+    .fold(current, Layout::overlay)
+    .apply_rules();
+  let anchored = match options.reanchor {
+    true => merged.reanchor(),
+    false => merged,
+  };
+  let target = match options.origin {
+    Some(ref origin) => anchored.recenter(origin).map_err(Error::Output)?,
+    None => anchored,
+  }
+  .pin(&load_pins(repo));
+  let commands = sequence.unwrap_or_else(|| target.serialize_commands());
+  Ok((target, commands))
+}
+
+/// Returns what was saved for the layout, if anything. A corrupt file is
+/// an error rather than a reason to silently ignore the saved layout, as
+/// is one with resolutions Sway would reject or values, such as
+/// transforms, that don't parse.
+fn load_saved(
+  repo: &Repository,
+  layout: &Layout,
+  max: u32,
+  mode: FingerprintMode,
+) -> Result<Option<Saved>, Error> {
+  let id = layout.fingerprint_with(mode);
+  match repo.load::<Stored>(id.clone()).map(Saved::from) {
+    Ok(saved) => saved
+      .layout
+      .check_resolutions(max)
+      .map(|_| Some(saved))
+      .map_err(|reason| Error::Load(StorageError::Invalid(repo.path(id), reason))),
+    Err(err @ StorageError::Empty(_))
+    | Err(err @ StorageError::Directory(_))
+    | Err(err @ StorageError::Json(_))
+    | Err(err @ StorageError::Syntax(_)) => Err(Error::Load(err)),
+    Err(_) => Ok(None),
+  }
+}
+
+/// Ensures every monitor of the saved layout is connected. Layouts being
+/// saved per set of monitors, the one expected when none matches is the
+/// closest saved layout.
+fn check_expected(repo: &Repository, saved: Option<&Saved>, layout: &Layout) -> Result<(), Error> {
+  let expected = match saved {
+    Some(saved) => Some(saved.layout.clone()),
+    None => closest_saved(repo, layout),
+  };
+  match expected.map(|e| e.missing_from(layout)) {
+    Some(ref missing) if !missing.is_empty() => Err(Error::MissingOutputs(missing.clone())),
+    _ => Ok(()),
+  }
+}
+
+/// Returns the saved layout sharing the most monitors with the given one,
+/// if any shares at least one.
+fn closest_saved(repo: &Repository, layout: &Layout) -> Option<Layout> {
+  let saved = saved_layouts(repo.list().ok()?);
+  match layout.rank(saved.clone()).into_iter().next()? {
+    (_, Score::Partial(0)) => None,
+    (best, _) => saved
+      .into_iter()
+      .find(|(id, _)| *id == best)
+      .map(|(_, l)| l),
+  }
 }
 
 /// Merges saved configuration if found, or returns the current layout.
-fn merge_or_current(repo: Repository, layout: Layout) -> Layout {
-  match repo.load(layout.fingerprint()) {
-    Ok(l) => layout.merge(l),
-    Err(_) => layout,
+/// A saved layout that doesn't line up with the current one is ignored
+/// with a warning, unless in strict mode.
+fn merge_or_current(
+  saved: Option<Saved>,
+  layout: Layout,
+  options: &Options,
+) -> Result<Layout, Error> {
+  let s = match saved {
+    Some(s) => s,
+    None => return Ok(layout),
+  };
+  match layout.clone().merge_with(
+    s.layout,
+    s.merge_policy.unwrap_or_default(),
+    options.fingerprint,
+  ) {
+    Ok(merged) => Ok(merged),
+    Err(err) if options.strict => Err(Error::Merge(err)),
+    Err(err) => {
+      warn(options, &format!("{}, keeping the current layout", err));
+      Ok(layout)
+    }
   }
 }
 
+/// Returns the fragments to stack, in order. Unlike other configuration,
+/// a missing fragment is an error as it was asked for explicitly.
+fn load_fragments(repo: &Repository, options: &Options) -> Result<Vec<Fragment>, Error> {
+  let ids = match options.fragments.is_empty() {
+    true => repo.load(String::from(FRAGMENTS)).unwrap_or_default(),
+    false => options.fragments.clone(),
+  };
+  ids
+    .into_iter()
+    .map(|id| repo.load(id).map_err(Error::Load))
+    .collect()
+}
+
+/// Returns the scaling policy, if any was configured.
+fn load_scaling(repo: &Repository) -> Scaling {
+  repo.load(String::from(SCALING)).unwrap_or_default()
+}
+
+/// Returns the pinned outputs, if any were configured.
+fn load_pins(repo: &Repository) -> Pins {
+  repo.load(String::from(PINS)).unwrap_or_default()
+}
+
 /// Execute a Sway command and ensure it is successful.
-fn run_output_command((mut ipc, message): (Ipc, Message)) -> Result<(), Error> {
-  match ipc
+fn run_output_command<T>((mut ipc, message): (T, Message)) -> Result<(), Error>
+where
+  T: Transport,
+{
+  ipc
     .roundtrip(message.to_bytes())
     .map_err(Error::Ipc)
-    .map(Response::bulk_scan)?
-  {
-    true => Ok(()),
-    false => Err(Error::Configuration(message)),
-  }
+    .and_then(|data| Response::bulk_scan(data).map_err(Error::Response))?
+    .map_err(|reason| Error::Configuration(message, reason))
 }
 
 /// AutoSway could not perform correctly.
@@ -104,8 +1506,34 @@ pub enum Error {
   ActiveLayout(serde_json::error::Error),
   /// Current layout could not be persisted.
   Save(repository::StorageError),
+  /// Saved layouts could not be read.
+  Load(repository::StorageError),
+  /// The saved layout could not be removed.
+  Delete(repository::StorageError),
   /// Configuration of one of the outputs failed.
-  Configuration(message::Message),
+  Configuration(message::Message, Option<String>),
+  /// Some of the Sway instances failed, keyed by socket path.
+  Instances(Vec<(String, Error)>),
+  /// The requested change of an output is not possible.
+  Output(String),
+  /// More outputs were detected than allowed, as found and as allowed.
+  TooManyOutputs(usize, usize),
+  /// Signal handlers could not be installed.
+  Signal(io::Error),
+  /// Monitors the saved layout expects are not connected, by OEM
+  /// identifier.
+  MissingOutputs(Vec<String>),
+  /// The saved layout doesn't line up with the current one.
+  Merge(MergeError),
+  /// Outputs of the layout to configure overlap or are out of reach.
+  InvalidLayout(String),
+  /// Sway answered a command with something else than responses.
+  Response(serde_json::error::Error),
+  /// The layout could not be written out for printing.
+  Print(serde_json::error::Error),
+  /// Watching was asked of several Sway instances at once, as many as
+  /// given.
+  WatchMany(usize),
 }
 
 impl fmt::Display for Error {
@@ -114,27 +1542,1999 @@ impl fmt::Display for Error {
       Error::Ipc(ref err) => write!(f, "could not communicate with sway: {}", err),
       Error::ActiveLayout(ref err) => write!(f, "active layout request failed: {}", err),
       Error::Save(ref err) => write!(f, "could not persist layout: {}", err),
-      Error::Configuration(ref mess) => write!(f, "error applying settings: {:?}", mess),
+      Error::Load(ref err) => write!(f, "could not read saved layouts: {}", err),
+      Error::Delete(StorageError::Io(ref err)) if err.kind() == io::ErrorKind::NotFound => {
+        write!(f, "no saved layout for this setup")
+      }
+      Error::Delete(ref err) => write!(f, "could not delete saved layout: {}", err),
+      Error::Configuration(ref mess, None) => write!(f, "error applying settings: {:?}", mess),
+      Error::Configuration(ref mess, Some(ref reason)) => {
+        write!(f, "error applying settings: {:?}: {}", mess, reason)
+      }
+      Error::Instances(ref failures) => write!(
+        f,
+        "{}",
+        failures
+          .iter()
+          .map(|(path, err)| format!("{}: {}", path, err))
+          .collect::<Vec<String>>()
+          .join("\n")
+      ),
+      Error::Output(ref reason) => write!(f, "could not change output: {}", reason),
+      Error::TooManyOutputs(found, max) => {
+        write!(
+          f,
+          "refusing to configure {}, limit is {}",
+          outputs(found),
+          max
+        )
+      }
+      Error::Signal(ref err) => write!(f, "could not handle signals: {}", err),
+      Error::Merge(ref err) => write!(f, "could not merge layouts: {}", err),
+      Error::InvalidLayout(ref reason) => write!(f, "refusing to configure layout: {}", reason),
+      Error::Response(ref err) => write!(f, "invalid response from sway: {}", err),
+      Error::Print(ref err) => write!(f, "could not print layout: {}", err),
+      Error::WatchMany(count) => write!(
+        f,
+        "can only watch a single sway instance, {} sockets given",
+        count
+      ),
+      Error::MissingOutputs(ref ids) => {
+        write!(f, "expected outputs not connected: {}", ids.join(", "))
+      }
     }
   }
 }
 
 impl error::Error for Error {
-  fn description(&self) -> &str {
-    match *self {
-      Error::Ipc(ref err) => err.description(),
-      Error::ActiveLayout(ref err) => err.description(),
-      Error::Save(ref err) => err.description(),
-      Error::Configuration(_) => "",
-    }
-  }
-
-  fn cause(&self) -> Option<&dyn error::Error> {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
     match *self {
       Error::Ipc(ref err) => Some(err),
       Error::ActiveLayout(ref err) => Some(err),
       Error::Save(ref err) => Some(err),
-      Error::Configuration(_) => None,
+      Error::Load(ref err) => Some(err),
+      Error::Delete(ref err) => Some(err),
+      Error::Configuration(_, _) => None,
+      Error::Instances(_) => None,
+      Error::Output(_) => None,
+      Error::TooManyOutputs(_, _) => None,
+      Error::Signal(ref err) => Some(err),
+      Error::MissingOutputs(_) => None,
+      Error::Merge(ref err) => Some(err),
+      Error::InvalidLayout(_) => None,
+      Error::Response(ref err) => Some(err),
+      Error::Print(ref err) => Some(err),
+      Error::WatchMany(_) => None,
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::cell::{Cell, RefCell};
+  use std::path::Path;
+
+  #[test]
+  fn every_instance_should_receive_the_commands() {
+    let (a, b) = (FakeSway::new(), FakeSway::new());
+    let connect = |path: String| match path.as_ref() {
+      "a" => Ok(a.clone()),
+      _ => Ok(b.clone()),
+    };
+    with_store(|root| {
+      run_on_each(
+        sockets(&["a", "b"]),
+        connect,
+        root,
+        Action::Auto,
+        Options::default(),
+        &AtomicBool::new(false),
+      )
+    })
+    .unwrap();
+    assert_eq!(vec![COMMAND], a.commands());
+    assert_eq!(vec![COMMAND], b.commands());
+  }
+
+  #[test]
+  fn watching_several_instances_should_be_refused() {
+    let connect = |_: String| -> Result<FakeSway, io::Error> { panic!("should not connect") };
+    let actual = with_store(|root| {
+      run_on_each(
+        sockets(&["a", "b"]),
+        connect,
+        root,
+        Action::Watch,
+        Options::default(),
+        &AtomicBool::new(false),
+      )
+    });
+    assert!(matches!(actual, Err(Error::WatchMany(2))));
+  }
+
+  #[test]
+  fn store_actions_should_not_connect_to_sway() {
+    let connect = |_| Err::<FakeSway, _>(io::Error::from(io::ErrorKind::NotFound));
+    let actual = with_store(|root| {
+      let action = Action::Saved(None);
+      run_on_each(
+        sockets(&["a", "b"]),
+        connect,
+        root,
+        action,
+        Options::default(),
+        &AtomicBool::new(false),
+      )
+    });
+    assert_eq!(String::new(), actual.unwrap());
+  }
+
+  #[test]
+  fn outputs_of_several_instances_should_be_labeled_with_their_socket() {
+    let sway = FakeSway::new();
+    let connect = |_| Ok(sway.clone());
+    let actual = with_store(|root| {
+      let action = Action::Fingerprint;
+      run_on_each(
+        sockets(&["a", "b"]),
+        connect,
+        root,
+        action,
+        Options::default(),
+        &AtomicBool::new(false),
+      )
+    });
+    let fingerprint = sway.layout().fingerprint();
+    assert_eq!(
+      format!("a: {}\nb: {}", fingerprint, fingerprint),
+      actual.unwrap()
+    );
+  }
+
+  #[test]
+  fn a_failing_instance_should_not_abort_the_others() {
+    let b = FakeSway::new();
+    let connect = |path: String| match path.as_ref() {
+      "a" => Err(io::Error::from(io::ErrorKind::NotFound)),
+      _ => Ok(b.clone()),
+    };
+    let actual = with_store(|root| {
+      run_on_each(
+        sockets(&["a", "b"]),
+        connect,
+        root,
+        Action::Auto,
+        Options::default(),
+        &AtomicBool::new(false),
+      )
+    });
+    assert_eq!(vec![COMMAND], b.commands());
+    match actual {
+      Err(Error::Instances(failures)) => {
+        assert_eq!(1, failures.len());
+        assert_eq!(String::from("a"), failures[0].0);
+      }
+      _ => panic!("expected the failing instance to be reported"),
+    }
+  }
+
+  #[test]
+  fn reload_should_be_sent_after_the_output_commands_when_requested() {
+    let sway = FakeSway::new();
+    let options = Options {
+      reload_after: true,
+      ..Options::default()
+    };
+    with_store(|root| auto(&sway, root, options)).unwrap();
+    assert_eq!(vec![COMMAND, "reload"], sway.commands());
+  }
+
+  #[test]
+  fn reload_should_not_be_sent_by_default() {
+    let sway = FakeSway::new();
+    with_store(|root| auto(&sway, root, Options::default())).unwrap();
+    assert_eq!(vec![COMMAND], sway.commands());
+  }
+
+  #[test]
+  fn a_recorded_sequence_should_be_replayed_in_order() {
+    let sway = FakeSway::new();
+    let sequence = vec!["output HDMI-2 disable", "output eDP1 enable"];
+    with_store(|root| {
+      let saved = Saved {
+        command_sequence: Some(sequence.iter().map(|c| String::from(*c)).collect()),
+        ..Saved::new(sway.layout())
+      };
+      Repository::new(root.clone())
+        .save(sway.layout().fingerprint(), saved)
+        .unwrap();
+      auto(&sway, root, Options::default())
+    })
+    .unwrap();
+    assert_eq!(sequence, sway.commands());
+  }
+
+  #[test]
+  fn saving_under_a_name_should_record_the_fingerprint() {
+    let sway = FakeSway::new();
+    let action = Action::Save(Some(String::from("desk")));
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root.clone()),
+        action,
+        &Options::default(),
+      )
+      .unwrap();
+      let repo = Repository::new(root);
+      assert!(repo.load::<Saved>(sway.layout().fingerprint()).is_err());
+      repo.load_named::<Saved>("desk").unwrap()
+    });
+    assert_eq!(Some(sway.layout().fingerprint()), actual.fingerprint);
+    assert_eq!(sway.layout(), actual.layout);
+  }
+
+  #[test]
+  fn applying_by_name_should_merge_the_named_layout() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let named: Layout = serde_json::from_str(
+      &two_monitors().replace("\"x\": 1920, \"y\": 0", "\"x\": 0, \"y\": 1080"),
+    )
+    .unwrap();
+    let action = Action::Apply(String::from("desk"));
+    with_store(|root| {
+      let repo = Repository::new(root);
+      repo.save_named("desk", Saved::new(named)).unwrap();
+      run_once(sway.clone(), "test", repo, action, &Options::default())
+    })
+    .unwrap();
+    assert_eq!(
+      vec![
+        "output eDP1 enable res 1920x1080 pos 0 0 transform normal",
+        "output HDMI-A-1 enable res 1920x1080 pos 0 1080 transform normal"
+      ],
+      sway.commands()
+    );
+  }
+
+  #[test]
+  fn applying_a_name_saved_for_other_outputs_should_fail() {
+    let sway = FakeSway::new();
+    let other: Layout = serde_json::from_str(&OUTPUTS.replace("12345", "67890")).unwrap();
+    let action = Action::Apply(String::from("desk"));
+    let actual = with_store(|root| {
+      let repo = Repository::new(root);
+      repo.save_named("desk", Saved::new(other)).unwrap();
+      run_once(sway.clone(), "test", repo, action, &Options::default())
+    });
+    assert!(matches!(actual, Err(Error::Merge(_))));
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn applying_an_unknown_name_should_fail() {
+    let sway = FakeSway::new();
+    let action = Action::Apply(String::from("travel"));
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        action,
+        &Options::default(),
+      )
+    });
+    match actual {
+      Err(Error::Load(_)) => (),
+      _ => panic!("expected the missing layout to be reported"),
+    }
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn saving_should_record_the_sequence_when_requested() {
+    let sway = FakeSway::new();
+    let options = Options {
+      record_sequence: true,
+      ..Options::default()
+    };
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root.clone()),
+        Action::Save(None),
+        &options,
+      )
+      .unwrap();
+      Repository::new(root)
+        .load::<Saved>(sway.layout().fingerprint())
+        .unwrap()
+    });
+    assert_eq!(Some(vec![String::from(COMMAND)]), actual.command_sequence);
+  }
+
+  #[test]
+  fn saving_should_record_when_the_layout_was_captured() {
+    let sway = FakeSway::new();
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root.clone()),
+        Action::Save(None),
+        &Options::default(),
+      )
+      .unwrap();
+      Repository::new(root)
+        .load::<Saved>(sway.layout().fingerprint())
+        .unwrap()
+    });
+    assert!(actual.saved_at.unwrap().ends_with('Z'));
+    assert_eq!(sway.layout(), actual.layout);
+  }
+
+  #[test]
+  fn an_unchanged_layout_should_not_be_applied_twice() {
+    let sway = FakeSway::new();
+    with_store(|root| {
+      auto(&sway, root.clone(), Options::default()).unwrap();
+      auto(&sway, root, Options::default()).unwrap();
+    });
+    assert_eq!(vec![COMMAND], sway.commands());
+  }
+
+  #[test]
+  fn force_should_apply_an_unchanged_layout_again() {
+    let sway = FakeSway::new();
+    let options = Options {
+      force: true,
+      ..Options::default()
+    };
+    with_store(|root| {
+      auto(&sway, root.clone(), options.clone()).unwrap();
+      auto(&sway, root, options).unwrap();
+    });
+    assert_eq!(vec![COMMAND, COMMAND], sway.commands());
+  }
+
+  #[test]
+  fn an_empty_saved_layout_should_be_reported() {
+    let sway = FakeSway::new();
+    let actual = with_store(|root| {
+      std::fs::write(
+        std::path::Path::new(&root).join(sway.layout().fingerprint()),
+        "",
+      )
+      .unwrap();
+      auto(&sway, root, Options::default())
+    });
+    match actual {
+      Err(Error::Load(StorageError::Empty(_))) => (),
+      _ => panic!("expected the empty file to be reported"),
+    }
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn an_unconfirmed_layout_should_be_reverted() {
+    let sway = FakeSway::new();
+    with_store(|root| try_saved_disabled_layout(&sway, root, false));
+    assert_eq!(vec!["output eDP1 disable", COMMAND], sway.commands());
+  }
+
+  #[test]
+  fn a_confirmed_layout_should_be_kept() {
+    let sway = FakeSway::new();
+    with_store(|root| try_saved_disabled_layout(&sway, root, true));
+    assert_eq!(vec!["output eDP1 disable"], sway.commands());
+  }
+
+  #[test]
+  fn notify_should_print_the_applied_layout_status() {
+    let sway = FakeSway::new();
+    let options = Options {
+      notify: true,
+      ..Options::default()
+    };
+    let actual = with_store(|root| auto(&sway, root, options)).unwrap();
+    let expected = format!(
+      "STATUS=applied layout {} (1 output)",
+      sway.layout().fingerprint()
+    );
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn an_absurd_saved_resolution_should_be_reported() {
+    let sway = FakeSway::new();
+    let actual = with_store(|root| {
+      let saved: Layout = serde_json::from_str(&OUTPUTS.replace("1920", "0")).unwrap();
+      Repository::new(root.clone())
+        .save(sway.layout().fingerprint(), Saved::new(saved))
+        .unwrap();
+      auto(&sway, root, Options::default())
+    });
+    match actual {
+      Err(Error::Load(StorageError::Invalid(_, _))) => (),
+      _ => panic!("expected the invalid resolution to be reported"),
+    }
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn an_invalid_saved_transform_should_be_reported() {
+    let sway = FakeSway::new();
+    let actual = with_store(|root| {
+      let saved = OUTPUTS.replace("\"normal\"", "\"90deg\"");
+      std::fs::write(
+        Repository::new(root.clone()).path(sway.layout().fingerprint()),
+        saved,
+      )
+      .unwrap();
+      auto(&sway, root, Options::default())
+    });
+    match actual {
+      Err(Error::Load(StorageError::Json(_))) => (),
+      _ => panic!("expected the invalid transform to be reported"),
+    }
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn overlapping_outputs_should_not_be_configured() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let saved = two_monitors().replace("\"x\": 1920", "\"x\": 960");
+    let actual = with_saved(&sway, &saved, |repo| {
+      apply_configuration(
+        repo,
+        sway.clone(),
+        "test",
+        sway.layout(),
+        &Options::default(),
+        &clock::System,
+      )
+    });
+    match actual {
+      Err(Error::InvalidLayout(ref reason)) => assert_eq!("eDP1 overlaps HDMI-A-1", reason),
+      _ => panic!("expected the overlap to be reported"),
+    }
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn peek_should_return_the_merged_target_without_applying_it() {
+    let sway = FakeSway::new();
+    let saved: Layout = serde_json::from_str(&OUTPUTS.replace("1920", "2560")).unwrap();
+    let actual = with_store(|root| {
+      let repo = Repository::new(root);
+      repo
+        .save(sway.layout().fingerprint(), Saved::new(saved.clone()))
+        .unwrap();
+      peek_target(sway.clone(), &repo, &Options::default())
+    })
+    .unwrap();
+    assert_eq!(saved, actual);
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn transform_should_rotate_the_matching_output() {
+    let sway = FakeSway::new();
+    let action = Action::Transform(String::from("eDP1"), String::from("90"));
+    with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        action,
+        &Options::default(),
+      )
+    })
+    .unwrap();
+    assert_eq!(
+      vec!["output eDP1 enable res 1920x1080 pos 0 0 transform 90"],
+      sway.commands()
+    );
+  }
+
+  #[test]
+  fn saving_a_transform_should_keep_the_rest_of_the_saved_layout() {
+    let sway = FakeSway::new();
+    let options = Options {
+      save: true,
+      ..Options::default()
+    };
+    let mut workspaces = workspace::Assignments::new();
+    workspaces.insert(String::from("1"), String::from("Samsung|XYZ|12345"));
+    let saved = Saved {
+      command_sequence: Some(vec![String::from(COMMAND)]),
+      merge_policy: Some(MergePolicy::KeepModes),
+      workspaces: Some(workspaces),
+      inputs: Some(input::parse(br#"[{"identifier": "1:1:kbd", "type": "keyboard"}]"#).unwrap()),
+      ..Saved::new(sway.layout())
+    };
+    let actual = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      repo.save(sway.layout().fingerprint(), &saved).unwrap();
+      let action = Action::Transform(String::from("eDP1"), String::from("90"));
+      run_once(sway.clone(), "test", repo, action, &options).unwrap();
+      Repository::new(root)
+        .load::<Stored>(sway.layout().fingerprint())
+        .map(Saved::from)
+        .unwrap()
+    });
+    assert_eq!(
+      Some(Transform::Rot90),
+      actual.layout.outputs()[0].transform()
+    );
+    assert_eq!(
+      Saved {
+        layout: saved.layout.clone().set_transform("eDP1", "90").unwrap(),
+        ..saved
+      },
+      actual
+    );
+  }
+
+  #[test]
+  fn workspaces_should_be_listed_by_output() {
+    let mut sway = FakeSway::new();
+    sway.workspaces = String::from(WORKSPACES);
+    let action = Action::Workspaces;
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        action,
+        &Options::default(),
+      )
+    });
+    assert_eq!("eDP1\n  1\n  2: web (focused)", actual.unwrap());
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn disable_should_turn_off_the_named_output() {
+    let sway = FakeSway::new();
+    let action = Action::Disable(String::from("eDP1"));
+    with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        action,
+        &Options::default(),
+      )
+    })
+    .unwrap();
+    assert_eq!(vec!["output eDP1 disable"], sway.commands());
+  }
+
+  #[test]
+  fn disable_should_fail_on_an_unknown_output() {
+    let sway = FakeSway::new();
+    let action = Action::Disable(String::from("DP-9"));
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        action,
+        &Options::default(),
+      )
+    });
+    match actual {
+      Err(Error::Output(ref message)) => assert_eq!("unknown output: DP-9", message),
+      _ => panic!("expected the unknown output to be reported"),
+    }
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn power_off_should_wait_for_the_delay_to_elapse() {
+    let sway = FakeSway::new();
+    let clock = clock::Fake::at(SystemTime::UNIX_EPOCH);
+    let delay = Duration::from_secs(30 * 60);
+    power_off_after(sway.clone(), &sway.layout(), "eDP1", delay, &clock).unwrap();
+    assert_eq!(SystemTime::UNIX_EPOCH + delay, clock.now());
+    assert_eq!(vec!["output eDP1 power off"], sway.commands());
+  }
+
+  #[test]
+  fn power_off_should_fail_on_an_unknown_output_without_waiting() {
+    let sway = FakeSway::new();
+    let clock = clock::Fake::at(SystemTime::UNIX_EPOCH);
+    let delay = Duration::from_secs(60);
+    let actual = power_off_after(sway.clone(), &sway.layout(), "DP-9", delay, &clock);
+    assert!(matches!(actual, Err(Error::Output(_))));
+    assert_eq!(0, clock.sleeps());
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn enable_should_restore_the_saved_position() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let saved = two_monitors().replace("\"x\": 1920", "\"x\": 2560");
+    with_saved(&sway, &saved, |repo| {
+      toggle_output(
+        repo,
+        sway.clone(),
+        sway.layout(),
+        "HDMI-A-1",
+        true,
+        &Options::default(),
+      )
+    })
+    .unwrap();
+    assert_eq!(
+      vec!["output HDMI-A-1 enable res 1920x1080 pos 2560 0 transform normal"],
+      sway.commands()
+    );
+  }
+
+  #[test]
+  fn transform_should_fail_on_an_unknown_output() {
+    let sway = FakeSway::new();
+    let action = Action::Transform(String::from("DP-9"), String::from("90"));
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        action,
+        &Options::default(),
+      )
+    });
+    match actual {
+      Err(Error::Output(_)) => (),
+      _ => panic!("expected the unknown output to be reported"),
+    }
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn a_layout_with_too_many_outputs_should_be_refused() {
+    let sway = FakeSway::new();
+    let options = Options {
+      max_outputs: Some(0),
+      ..Options::default()
+    };
+    match with_store(|root| auto(&sway, root, options)) {
+      Err(Error::TooManyOutputs(1, 0)) => (),
+      _ => panic!("expected the layout to be refused"),
+    }
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn a_layout_within_the_output_limit_should_be_applied() {
+    let sway = FakeSway::new();
+    let options = Options {
+      max_outputs: Some(1),
+      ..Options::default()
+    };
+    with_store(|root| auto(&sway, root, options)).unwrap();
+    assert_eq!(vec![COMMAND], sway.commands());
+  }
+
+  #[test]
+  fn the_self_test_should_pass() {
+    assert_eq!("self-test passed: 1 output parsed", self_test().unwrap());
+  }
+
+  #[test]
+  fn the_apply_delay_should_pause_between_commands() {
+    let sway = FakeSway::new();
+    let clock = clock::Fake::at(SystemTime::UNIX_EPOCH);
+    let options = Options {
+      apply_delay: Duration::from_millis(50),
+      ..Options::default()
+    };
+    with_store(|root| {
+      let repo = Repository::new(root);
+      let saved = Saved {
+        command_sequence: Some(vec![COMMAND.to_string(); 3]),
+        ..Saved::new(sway.layout())
+      };
+      repo.save(sway.layout().fingerprint(), saved).unwrap();
+      apply_configuration(&repo, sway.clone(), "test", sway.layout(), &options, &clock)
+    })
+    .unwrap();
+    assert_eq!(3, sway.commands().len());
+    assert_eq!(2, clock.sleeps());
+    assert_eq!(
+      SystemTime::UNIX_EPOCH + Duration::from_millis(100),
+      clock.now()
+    );
+  }
+
+  #[test]
+  fn output_commands_should_be_sent_in_a_single_message() {
+    let sway = FakeSway::new();
+    let clock = clock::Fake::at(SystemTime::UNIX_EPOCH);
+    let commands = vec![COMMAND.to_string(); 3];
+    send_commands(sway.clone(), commands, &Options::default(), &clock).unwrap();
+    assert_eq!(3, sway.commands().len());
+    assert_eq!(1, sway.runs.get());
+  }
+
+  #[test]
+  fn output_commands_should_be_sent_one_by_one_if_asked_to() {
+    let sway = FakeSway::new();
+    let clock = clock::Fake::at(SystemTime::UNIX_EPOCH);
+    let options = Options {
+      one_by_one: true,
+      ..Options::default()
+    };
+    let commands = vec![COMMAND.to_string(); 3];
+    send_commands(sway.clone(), commands, &options, &clock).unwrap();
+    assert_eq!(3, sway.commands().len());
+    assert_eq!(3, sway.runs.get());
+  }
+
+  #[test]
+  fn a_single_connection_should_stop_at_the_first_failing_command() {
+    let mut sway = Rejecting(1, Vec::new());
+    let clock = clock::Fake::at(SystemTime::UNIX_EPOCH);
+    let options = Options {
+      one_by_one: true,
+      ..Options::default()
+    };
+    let commands = vec![COMMAND.to_string(); 3];
+    let actual = send_commands(&mut sway, commands, &options, &clock);
+    assert!(matches!(actual, Err(Error::Configuration(_, Some(_)))));
+    assert_eq!(2, sway.1.len());
+  }
+
+  #[test]
+  fn the_focused_workspace_should_be_detected() {
+    let mut sway = FakeSway::new();
+    sway.workspaces = String::from(WORKSPACES);
+    let actual = request_workspaces(&mut sway).unwrap();
+    assert_eq!(Some(String::from("2: web")), workspace::focused(&actual));
+  }
+
+  #[test]
+  fn auto_should_enable_the_focused_output_when_nothing_is_saved() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors().replacen("\"active\": true", "\"active\": false", 1);
+    sway.tree = String::from(
+      r#"{"type": "root", "nodes": [{"type": "output", "name": "eDP1", "focused": true}]}"#,
+    );
+    with_store(|root| auto(&sway, root, Options::default())).unwrap();
+    assert_eq!(
+      Some(&String::from(
+        "output eDP1 enable res 1920x1080 pos 0 0 transform normal"
+      )),
+      sway.commands().first()
+    );
+  }
+
+  #[test]
+  fn only_actions_needing_workspaces_or_the_tree_should_request_them() {
+    let mut sway = FakeSway::new();
+    sway.workspaces = String::from("[{");
+    sway.tree = String::from("{");
+    let (listed, saved) = with_store(|root| {
+      let list = Action::List(Format::Text);
+      let listed = run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root.clone()),
+        list,
+        &Options::default(),
+      );
+      let saved = run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        Action::Save(None),
+        &Options::default(),
+      );
+      (listed, saved)
+    });
+    assert!(listed.is_ok());
+    assert!(matches!(saved, Err(Error::Response(_))));
+  }
+
+  #[test]
+  fn the_tree_should_not_be_requested_once_a_layout_is_saved() {
+    let mut sway = FakeSway::new();
+    sway.tree = String::from("{");
+    let (unsaved, saved) = with_store(|root| {
+      let unsaved = auto(&sway, root.clone(), Options::default());
+      silently_save_layout(
+        Repository::new(root.clone()),
+        sway.layout(),
+        &[],
+        &[],
+        None,
+        &Options::default(),
+      )
+      .unwrap();
+      (unsaved, auto(&sway, root, Options::default()))
+    });
+    assert!(matches!(unsaved, Err(Error::Response(_))));
+    assert!(saved.is_ok());
+  }
+
+  #[test]
+  fn focus_should_be_restored_last() {
+    let mut sway = FakeSway::new();
+    sway.workspaces = String::from(WORKSPACES);
+    let options = Options {
+      reload_after: true,
+      ..Options::default()
+    };
+    with_store(|root| {
+      auto(&sway, root.clone(), options.clone()).unwrap();
+      auto(&sway, root, options).unwrap();
+    });
+    assert_eq!(
+      vec![COMMAND, "reload", r#"workspace "2: web""#],
+      sway.commands()
+    );
+  }
+
+  #[test]
+  fn watch_mode_should_persist_its_final_state_on_shutdown() {
+    let sway = FakeSway::new();
+    let shutdown = AtomicBool::new(true);
+    let actual = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      watch_outputs(
+        sway.clone(),
+        sway.clone(),
+        "test",
+        repo,
+        &Options::default(),
+        &shutdown,
+      )
+      .unwrap();
+      Repository::new(root)
+        .load::<HashMap<String, watch::State>>(String::from(WATCH_STATE))
+        .unwrap()
+    });
+    assert_eq!(
+      watch::State {
+        last_layout: Some(sway.layout().fingerprint()),
+        stopped: watch::Stop::Shutdown,
+      },
+      actual["test"]
+    );
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn watch_state_should_not_be_taken_for_a_layout() {
+    let sway = FakeSway::new();
+    let shutdown = AtomicBool::new(true);
+    let (ids, listed) = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      watch_outputs(
+        sway.clone(),
+        sway.clone(),
+        "test",
+        repo,
+        &Options::default(),
+        &shutdown,
+      )
+      .unwrap();
+      let repo = Repository::new(root);
+      (
+        repo.ids().unwrap(),
+        list_saved_layouts(repo, None, SystemTime::now()),
+      )
+    });
+    assert!(!ids.contains(&String::from(WATCH_STATE)));
+    assert_eq!("", listed.unwrap());
+  }
+
+  #[test]
+  fn watch_mode_should_restore_inputs_along_with_outputs() {
+    let sway = FakeSway::new();
+    let shutdown = AtomicBool::new(true);
+    let keyboard =
+      br#"[{"identifier": "1:1:keyboard", "type": "keyboard", "xkb_active_layout_index": 1}]"#;
+    with_store(|root| {
+      let repo = Repository::new(root);
+      let mut saved = Saved::new(serde_json::from_str(&OUTPUTS.replace("1920", "2560")).unwrap());
+      saved.inputs = Some(input::parse(keyboard).unwrap());
+      repo.save(sway.layout().fingerprint(), saved).unwrap();
+      watch_outputs(
+        sway.clone(),
+        sway.clone(),
+        "test",
+        repo,
+        &Options::default(),
+        &shutdown,
+      )
+      .unwrap();
+    });
+    assert_eq!(
+      Some(&String::from(r#"input "1:1:keyboard" xkb_switch_layout 1"#)),
+      sway.commands().last()
+    );
+    assert!(sway.commands().len() > 1);
+  }
+
+  #[test]
+  fn saving_inputs_should_fail_if_sway_does_not_report_them() {
+    let mut sway = FakeSway::new();
+    sway.inputs = String::from("[{");
+    let options = Options {
+      inputs: true,
+      ..Options::default()
+    };
+    let actual = with_store(|root| {
+      let repo = Repository::new(root);
+      run_once(sway.clone(), "test", repo, Action::Save(None), &options)
+    });
+    assert!(matches!(actual, Err(Error::Response(_))));
+  }
+
+  #[test]
+  fn watch_mode_should_survive_unparsable_outputs_until_disconnected() {
+    let mut sway = FakeSway::new();
+    sway.outputs = String::from("[{");
+    let shutdown = AtomicBool::new(false);
+    let actual = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      watch_outputs(
+        sway.clone(),
+        sway.clone(),
+        "test",
+        repo,
+        &Options::default(),
+        &shutdown,
+      )
+      .unwrap();
+      Repository::new(root)
+        .load::<HashMap<String, watch::State>>(String::from(WATCH_STATE))
+        .unwrap()
+    });
+    assert_eq!(
+      watch::State {
+        last_layout: None,
+        stopped: watch::Stop::Closed,
+      },
+      actual["test"]
+    );
+  }
+
+  #[test]
+  fn auto_should_request_outputs_only_once() {
+    let sway = FakeSway::new();
+    let options = Options {
+      reload_after: true,
+      notify: true,
+      reanchor: true,
+      ..Options::default()
+    };
+    with_store(|root| auto(&sway, root, options)).unwrap();
+    assert_eq!(1, sway.fetched(3));
+    assert_eq!(1, sway.fetched(1));
+    assert_eq!(1, sway.fetched(4));
+  }
+
+  #[test]
+  fn watch_mode_should_request_outputs_once_per_apply() {
+    let sway = FakeSway::new();
+    let shutdown = AtomicBool::new(true);
+    with_store(|root| {
+      let repo = Repository::new(root);
+      watch_outputs(
+        sway.clone(),
+        sway.clone(),
+        "test",
+        repo,
+        &Options::default(),
+        &shutdown,
+      )
+      .unwrap();
+    });
+    assert_eq!(1, sway.fetched(3));
+    assert_eq!(1, sway.fetched(1));
+  }
+
+  #[test]
+  fn the_saved_merge_policy_should_decide_what_is_kept() {
+    let live = FakeSway::new().layout();
+    let saved: Layout = serde_json::from_str(
+      &OUTPUTS
+        .replace("\"x\": 0", "\"x\": 100")
+        .replace("1920", "2560"),
+    )
+    .unwrap();
+    let with_policy = |policy| Saved {
+      merge_policy: Some(policy),
+      ..Saved::new(saved.clone())
+    };
+    let overridden = merge_or_current(
+      Some(with_policy(MergePolicy::Override)),
+      live.clone(),
+      &Options::default(),
+    )
+    .unwrap();
+    let positioned = merge_or_current(
+      Some(with_policy(MergePolicy::KeepPositions)),
+      live,
+      &Options::default(),
+    )
+    .unwrap();
+    assert_eq!(
+      vec!["output eDP1 enable res 2560x1080 pos 100 0 transform normal"],
+      overridden.serialize_commands()
+    );
+    assert_eq!(
+      vec!["output eDP1 enable res 2560x1080 pos 0 0 transform normal"],
+      positioned.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn fragments_should_be_stacked_in_order_over_the_live_layout() {
+    let sway = FakeSway::new();
+    let options = Options {
+      fragments: vec![String::from("base"), String::from("docked")],
+      ..Options::default()
+    };
+    with_store(|root| {
+      let repo = Repository::new(root.clone());
+      let base = serde_json::json!({"eDP1": {"rect": {"x": 1920, "y": 0, "width": 1920, "height": 1080}, "scale": 1.0}});
+      let docked = serde_json::json!({"eDP1": {"scale": 2.0}});
+      repo.save(String::from("base"), base).unwrap();
+      repo.save(String::from("docked"), docked).unwrap();
+      auto(&sway, root, options)
+    })
+    .unwrap();
+    assert_eq!(
+      vec!["output eDP1 enable res 1920x1080 pos 1920 0 transform normal scale 2"],
+      sway.commands()
+    );
+  }
+
+  #[test]
+  fn fragments_should_be_read_from_the_configuration_by_default() {
+    let sway = FakeSway::new();
+    with_store(|root| {
+      let repo = Repository::new(root.clone());
+      repo.save(String::from(FRAGMENTS), vec!["rotated"]).unwrap();
+      repo
+        .save(
+          String::from("rotated"),
+          serde_json::json!({"eDP1": {"transform": "90"}}),
+        )
+        .unwrap();
+      auto(&sway, root, Options::default())
+    })
+    .unwrap();
+    assert_eq!(
+      vec!["output eDP1 enable res 1920x1080 pos 0 0 transform 90"],
+      sway.commands()
+    );
+  }
+
+  #[test]
+  fn a_missing_fragment_should_be_reported() {
+    let sway = FakeSway::new();
+    let options = Options {
+      fragments: vec![String::from("missing")],
+      ..Options::default()
+    };
+    let actual = with_store(|root| auto(&sway, root, options));
+    assert!(matches!(actual, Err(Error::Load(_))));
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn normalizing_should_rewrite_legacy_layouts_canonically() {
+    let sway = FakeSway::new();
+    let id = sway.layout().fingerprint();
+    let (report, actual) = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      std::fs::write(repo.path(id.clone()), OUTPUTS).unwrap();
+      let report = normalize_store(repo).unwrap();
+      (
+        report,
+        std::fs::read_to_string(Repository::new(root).path(id.clone())).unwrap(),
+      )
+    });
+    assert_eq!(format!("{}: rewritten", id), report);
+    let expected = serde_json::to_value(Saved::new(sway.layout())).unwrap();
+    assert_eq!(serde_json::to_string_pretty(&expected).unwrap(), actual);
+  }
+
+  #[test]
+  fn normalizing_should_leave_canonical_files_untouched() {
+    let sway = FakeSway::new();
+    let (report, before, after) = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      let path = repo.path(String::from("layout"));
+      repo
+        .save(String::from("layout"), Saved::new(sway.layout()))
+        .unwrap();
+      repo.save(String::from(PINS), Pins::new()).unwrap();
+      std::fs::write(repo.path(String::from("junk")), "{").unwrap();
+      let before = std::fs::read(&path).unwrap();
+      let report = normalize_store(repo).unwrap();
+      (report, before, std::fs::read(&path).unwrap())
+    });
+    assert!(report.starts_with("junk: skipped"));
+    assert_eq!(1, report.lines().count());
+    assert_eq!(before, after);
+  }
+
+  #[test]
+  fn normalizing_should_not_turn_configuration_arrays_into_layouts() {
+    let actual = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      std::fs::write(repo.path(String::from(FRAGMENTS)), "[]").unwrap();
+      normalize_store(repo).unwrap();
+      std::fs::read_to_string(Repository::new(root).path(String::from(FRAGMENTS))).unwrap()
+    });
+    assert_eq!("[]", actual);
+  }
+
+  #[test]
+  fn staging_should_plan_commands_without_sending_them() {
+    let sway = FakeSway::new();
+    let staged = stage(&sway.layout());
+    assert_eq!(&[String::from(COMMAND)], staged.commands());
+    discard(staged);
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn committing_should_send_exactly_the_staged_commands() {
+    let sway = FakeSway::new();
+    let target: Layout = serde_json::from_str(&OUTPUTS.replace("\"x\": 0", "\"x\": 10")).unwrap();
+    let staged = stage(&target);
+    let expected = staged.commands().to_vec();
+    commit_on(sway.clone(), staged).unwrap();
+    assert_eq!(expected, sway.commands());
+    assert!(sway.fetched.borrow().is_empty());
+  }
+
+  #[test]
+  fn a_saved_layout_should_follow_monitors_across_connector_swaps() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    with_store(|root| {
+      silently_save_layout(
+        Repository::new(root.clone()),
+        sway.layout(),
+        &[],
+        &[],
+        None,
+        &Options::default(),
+      )
+      .unwrap();
+      sway.outputs = two_monitors()
+        .replace("eDP1", "swapped")
+        .replace("HDMI-A-1", "eDP1")
+        .replace("swapped", "HDMI-A-1")
+        .replace("\"x\": 1920", "\"x\": 0");
+      auto(&sway, root, Options::default())
+    })
+    .unwrap();
+    assert_eq!(
+      vec![
+        "output HDMI-A-1 enable res 1920x1080 pos 0 0 transform normal",
+        "output eDP1 enable res 1920x1080 pos 1920 0 transform normal",
+      ],
+      sway.commands()
+    );
+  }
+
+  #[test]
+  fn strict_mode_should_report_a_missing_expected_monitor() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let strict = Options {
+      strict: true,
+      ..Options::default()
+    };
+    let (lenient, actual) = with_store(|root| {
+      silently_save_layout(
+        Repository::new(root.clone()),
+        sway.layout(),
+        &[],
+        &[],
+        None,
+        &Options::default(),
+      )
+      .unwrap();
+      sway.outputs = String::from(OUTPUTS);
+      (
+        auto(&sway, root.clone(), Options::default()),
+        auto(&sway, root, strict.clone()),
+      )
+    });
+    assert!(lenient.is_ok());
+    match actual {
+      Err(Error::MissingOutputs(ids)) => assert_eq!(vec!["Samsung|XYZ|67890"], ids),
+      _ => panic!("expected missing outputs"),
+    }
+  }
+
+  #[test]
+  fn strict_mode_should_apply_when_every_monitor_is_connected() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let strict = Options {
+      strict: true,
+      ..Options::default()
+    };
+    with_store(|root| {
+      silently_save_layout(
+        Repository::new(root.clone()),
+        sway.layout(),
+        &[],
+        &[],
+        None,
+        &Options::default(),
+      )
+      .unwrap();
+      auto(&sway, root, strict)
+    })
+    .unwrap();
+    assert_eq!(2, sway.commands().len());
+  }
+
+  #[test]
+  fn applying_changes_should_send_nothing_for_an_identical_layout() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let sent = with_store(|root| {
+      apply_changes(
+        sway.clone(),
+        "test",
+        &Repository::new(root),
+        &Options::default(),
+      )
+    });
+    assert!(sent.unwrap().is_empty());
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn a_saved_layout_with_a_disabled_output_should_still_load() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let mut saved: serde_json::Value = serde_json::from_str(&two_monitors()).unwrap();
+    saved[1]["active"] = false.into();
+    saved[1]["rect"] = serde_json::json!({"x": 0, "y": 0, "width": 0, "height": 0});
+    let actual = with_store(|root| {
+      let layout: Layout = serde_json::from_value(saved).unwrap();
+      Repository::new(root.clone())
+        .save(sway.layout().fingerprint(), Saved::new(layout))
+        .unwrap();
+      auto(&sway, root, Options::default())
+    });
+    assert!(actual.is_ok());
+    assert!(sway
+      .commands()
+      .contains(&String::from("output HDMI-A-1 disable")));
+  }
+
+  #[test]
+  fn applying_changes_should_only_send_the_changed_output() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let saved = two_monitors().replace("\"x\": 1920", "\"x\": -1920");
+    let sent = with_saved(&sway, &saved, |repo| {
+      apply_changes(sway.clone(), "test", repo, &Options::default())
+    });
+    let expected = vec!["output HDMI-A-1 enable res 1920x1080 pos -1920 0 transform normal"];
+    assert_eq!(expected, sent.unwrap());
+    assert_eq!(expected, sway.commands());
+  }
+
+  #[test]
+  fn applying_changes_should_record_the_applied_layout() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let saved = two_monitors().replace("\"x\": 1920", "\"x\": -1920");
+    with_store(|root| {
+      let repo = Repository::new(root.clone());
+      let layout: Layout = serde_json::from_str(&saved).unwrap();
+      repo
+        .save(sway.layout().fingerprint(), Saved::new(layout))
+        .unwrap();
+      apply_changes(sway.clone(), "test", &repo, &Options::default()).unwrap();
+      auto(&sway, root, Options::default()).unwrap();
+    });
+    assert_eq!(1, sway.commands().len());
+  }
+
+  #[test]
+  fn applying_changes_should_send_every_changed_output() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let saved = two_monitors().replace("\"normal\"", "\"90\"");
+    let sent = with_saved(&sway, &saved, |repo| {
+      apply_changes(sway.clone(), "test", repo, &Options::default())
+    });
+    assert_eq!(
+      vec![
+        "output eDP1 enable res 1920x1080 pos 0 0 transform 90",
+        "output HDMI-A-1 enable res 1920x1080 pos 1920 0 transform 90"
+      ],
+      sent.unwrap()
+    );
+    assert_eq!(2, sway.commands().len());
+  }
+
+  #[test]
+  fn dry_run_should_print_the_planned_commands_without_sending_them() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let saved = two_monitors().replace("\"x\": 1920", "\"x\": 2560");
+    let actual = with_store(|root| {
+      let repo = Repository::new(root);
+      let layout: Layout = serde_json::from_str(&saved).unwrap();
+      repo
+        .save(sway.layout().fingerprint(), Saved::new(layout))
+        .unwrap();
+      run_once(
+        sway.clone(),
+        "test",
+        repo,
+        Action::DryRun,
+        &Options::default(),
+      )
+    });
+    assert_eq!(
+      "output eDP1 enable res 1920x1080 pos 0 0 transform normal\n\
+       output HDMI-A-1 enable res 1920x1080 pos 2560 0 transform normal",
+      actual.unwrap()
+    );
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn diff_should_show_how_the_saved_layout_differs() {
+    let mut sway = FakeSway::new();
+    sway.outputs = two_monitors();
+    let saved = two_monitors().replace("\"x\": 1920", "\"x\": 2560");
+    let actual = with_saved(&sway, &saved, |repo| {
+      diff_saved(repo, sway.layout(), &Options::default())
+    });
+    assert_eq!("HDMI-A-1: pos 1920,0 -> 2560,0", actual.unwrap());
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn diff_should_say_when_nothing_is_saved() {
+    let sway = FakeSway::new();
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        Action::Diff,
+        &Options::default(),
+      )
+    });
+    assert_eq!("no saved layout for this fingerprint", actual.unwrap());
+  }
+
+  #[test]
+  fn dry_run_should_print_the_current_layout_when_nothing_is_saved() {
+    let sway = FakeSway::new();
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        Action::DryRun,
+        &Options::default(),
+      )
+    });
+    assert_eq!(
+      "output eDP1 enable res 1920x1080 pos 0 0 transform normal",
+      actual.unwrap()
+    );
+    assert!(sway.commands().is_empty());
+  }
+
+  #[test]
+  fn a_lone_output_should_be_enabled_without_touching_the_store() {
+    let sway = FakeSway::new();
+    let general = FakeSway::new();
+    let missing = with_store(|root| {
+      auto(&general, root.clone(), Options::default()).unwrap();
+      let missing = Path::new(&root).join("missing");
+      auto(
+        &sway,
+        missing.to_str().unwrap().to_string(),
+        Options::default(),
+      )
+      .unwrap();
+      missing.exists()
+    });
+    assert_eq!(general.commands(), sway.commands());
+    assert_eq!(vec![COMMAND], sway.commands());
+    assert!(!missing);
+  }
+
+  #[test]
+  fn a_lone_output_should_take_the_fast_path_unless_a_layout_is_saved_for_it() {
+    let sway = FakeSway::new();
+    let (before, after) = with_store(|root| {
+      auto(&sway, root.clone(), Options::default()).unwrap();
+      let repo = Repository::new(root.clone());
+      let before = takes_fast_path(&repo, &sway.layout(), &Options::default());
+      silently_save_layout(
+        Repository::new(root),
+        sway.layout(),
+        &[],
+        &[],
+        None,
+        &Options::default(),
+      )
+      .unwrap();
+      (
+        before,
+        takes_fast_path(&repo, &sway.layout(), &Options::default()),
+      )
+    });
+    assert!(before);
+    assert!(!after);
+  }
+
+  #[test]
+  fn workspaces_should_be_assigned_back_only_if_they_exist() {
+    let mut sway = FakeSway::new();
+    sway.workspaces = String::from(WORKSPACES);
+    with_store(|root| {
+      let workspaces: Vec<Workspace> = serde_json::from_str(WORKSPACES).unwrap();
+      silently_save_layout(
+        Repository::new(root.clone()),
+        sway.layout(),
+        &workspaces,
+        &[],
+        None,
+        &Options::default(),
+      )
+      .unwrap();
+      sway.workspaces = WORKSPACES.replace(r#""name": "1""#, r#""name": "3""#);
+      auto(&sway, root, Options::default())
+    })
+    .unwrap();
+    assert_eq!(
+      vec![
+        COMMAND,
+        r#"workspace "2: web" output eDP1"#,
+        r#"workspace "2: web""#
+      ],
+      sway.commands()
+    );
+  }
+
+  #[test]
+  fn saved_layouts_metadata_should_be_printed() {
+    let sway = FakeSway::new();
+    let id = sway.layout().fingerprint();
+    let actual = with_store(|root| {
+      let repo = Repository::new(root);
+      let saved = Saved {
+        saved_at: Some(String::from("2000-02-29T12:34:56Z")),
+        hostname: Some(String::from("desk")),
+        ..Saved::new(sway.layout())
+      };
+      repo.save(id.clone(), saved).unwrap();
+      describe_saved(&repo, id).unwrap()
+    });
+    assert_eq!(
+      "version: 1\nsaved at: 2000-02-29T12:34:56Z\nhostname: desk\noutputs: 1",
+      actual
+    );
+  }
+
+  #[test]
+  fn show_should_print_where_a_layout_was_saved_before_it() {
+    let sway = FakeSway::new();
+    let id = sway.layout().fingerprint();
+    let actual = with_store(|root| {
+      let repo = Repository::new(root);
+      let saved = Saved {
+        saved_at: Some(String::from("2000-02-29T12:34:56Z")),
+        hostname: Some(String::from("desk")),
+        ..Saved::new(sway.layout())
+      };
+      repo.save(id.clone(), saved).unwrap();
+      show_saved(&repo, id).unwrap()
+    });
+    assert_eq!(
+      format!("# saved 2000-02-29T12:34:56Z on desk\n{}", COMMAND),
+      actual
+    );
+  }
+
+  #[test]
+  fn bare_layouts_metadata_should_be_unknown() {
+    let sway = FakeSway::new();
+    let id = sway.layout().fingerprint();
+    let actual = with_store(|root| {
+      let repo = Repository::new(root);
+      repo.save(id.clone(), sway.layout()).unwrap();
+      describe_saved(&repo, id).unwrap()
+    });
+    assert!(actual.contains("saved at: unknown\nhostname: unknown"));
+  }
+
+  #[test]
+  fn fingerprints_should_be_computed_from_raw_json() {
+    let sway = FakeSway::new();
+    assert_eq!(
+      sway.layout().fingerprint(),
+      fingerprint_of(OUTPUTS).unwrap()
+    );
+    assert!(fingerprint_of("{").is_err());
+  }
+
+  #[test]
+  fn fingerprints_should_be_computed_from_saved_files() {
+    let sway = FakeSway::new();
+    let saved = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      silently_save_layout(repo, sway.layout(), &[], &[], None, &Options::default()).unwrap();
+      std::fs::read_to_string(Repository::new(root).path(sway.layout().fingerprint())).unwrap()
+    });
+    assert_eq!(sway.layout().fingerprint(), fingerprint_of(&saved).unwrap());
+  }
+
+  #[test]
+  fn the_current_fingerprint_should_be_printed_without_any_saved_layout() {
+    let sway = FakeSway::new();
+    let action = Action::Fingerprint;
+    let actual = with_store(|root| {
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root),
+        action,
+        &Options::default(),
+      )
+    });
+    assert_eq!(sway.layout().fingerprint(), actual.unwrap());
+  }
+
+  #[test]
+  fn exported_layouts_should_be_imported_under_their_fingerprint() {
+    let sway = FakeSway::new();
+    let id = sway.layout().fingerprint();
+    let (imported, saved) = with_store(|root| {
+      let file = Path::new(&root).join("shared.json");
+      let file = file.to_str().unwrap();
+      let repo = Repository::new(root.clone());
+      repo.save(id.clone(), Saved::new(sway.layout())).unwrap();
+      export_layout(&repo, id.clone(), file).unwrap();
+      repo.delete(id.clone()).unwrap();
+      let imported = import_layout(&repo, file, &Options::default()).unwrap();
+      (imported, repo.load::<Saved>(id.clone()).unwrap())
+    });
+    assert_eq!(id, imported);
+    assert_eq!(sway.layout(), saved.layout);
+  }
+
+  #[test]
+  fn bare_layouts_should_be_imported() {
+    let actual = with_store(|root| {
+      let file = Path::new(&root).join("bare.json");
+      std::fs::write(&file, OUTPUTS).unwrap();
+      import_layout(
+        &Repository::new(root),
+        file.to_str().unwrap(),
+        &Options::default(),
+      )
+    });
+    assert_eq!(FakeSway::new().layout().fingerprint(), actual.unwrap());
+  }
+
+  #[test]
+  fn files_not_holding_a_layout_should_not_be_imported() {
+    let actual = with_store(|root| {
+      let file = Path::new(&root).join("junk.json");
+      std::fs::write(&file, r#"{"foo": 1}"#).unwrap();
+      import_layout(
+        &Repository::new(root),
+        file.to_str().unwrap(),
+        &Options::default(),
+      )
+    });
+    assert!(matches!(
+      actual,
+      Err(Error::Load(StorageError::Invalid(_, _)))
+    ));
+  }
+
+  #[test]
+  fn empty_layouts_should_not_be_imported() {
+    let actual = with_store(|root| {
+      let file = Path::new(&root).join("empty.json");
+      std::fs::write(&file, "[]").unwrap();
+      import_layout(
+        &Repository::new(root),
+        file.to_str().unwrap(),
+        &Options::default(),
+      )
+    });
+    assert!(matches!(
+      actual,
+      Err(Error::Load(StorageError::Invalid(_, _)))
+    ));
+  }
+
+  #[test]
+  fn inputs_should_be_saved_if_asked_to_and_restored_after_outputs() {
+    let mut sway = FakeSway::new();
+    sway.inputs = String::from(
+      r#"[{"identifier": "1:1:keyboard", "type": "keyboard", "xkb_active_layout_index": 1}]"#,
+    );
+    let options = Options {
+      inputs: true,
+      ..Options::default()
+    };
+    with_store(|root| {
+      let save = Action::Save(None);
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root.clone()),
+        save,
+        &options,
+      )
+      .unwrap();
+      auto(&sway, root, Options::default()).unwrap();
+    });
+    assert_eq!(
+      vec![COMMAND, r#"input "1:1:keyboard" xkb_switch_layout 1"#],
+      sway.commands()
+    );
+  }
+
+  #[test]
+  fn inputs_should_not_be_saved_unless_asked_to() {
+    let mut sway = FakeSway::new();
+    sway.inputs = String::from(
+      r#"[{"identifier": "1:1:keyboard", "type": "keyboard", "xkb_active_layout_index": 1}]"#,
+    );
+    with_store(|root| {
+      let save = Action::Save(None);
+      run_once(
+        sway.clone(),
+        "test",
+        Repository::new(root.clone()),
+        save,
+        &Options::default(),
+      )
+      .unwrap();
+      auto(&sway, root, Options::default()).unwrap();
+    });
+    assert_eq!(vec![COMMAND], sway.commands());
+  }
+
+  #[test]
+  fn errors_should_expose_their_source() {
+    use std::error::Error as _;
+    let ipc = Error::Ipc(io::Error::new(io::ErrorKind::BrokenPipe, "gone"));
+    let source = ipc.source().and_then(|e| e.downcast_ref::<io::Error>());
+    assert_eq!(Some(io::ErrorKind::BrokenPipe), source.map(io::Error::kind));
+    let configuration = Error::Configuration(
+      Message::RunCommand(String::from("reload")),
+      Some(String::from("nope")),
+    );
+    assert!(configuration.source().is_none());
+    assert!(configuration.to_string().ends_with(": nope"));
+  }
+
+  #[test]
+  fn errors_should_chain_down_to_the_root_cause() {
+    let missing = io::Error::new(io::ErrorKind::NotFound, "missing");
+    let load = Error::Load(StorageError::Io(missing));
+    let chain: Vec<String> =
+      std::iter::successors(Some(&load as &dyn error::Error), |e| e.source())
+        .map(|e| e.to_string())
+        .collect();
+    assert_eq!(3, chain.len());
+    assert_eq!("missing", chain[2]);
+    assert!(format!("{:?}", load).contains("missing"));
+  }
+
+  #[test]
+  fn saved_layouts_should_be_listed_with_their_number_of_outputs() {
+    let sway = FakeSway::new();
+    let actual = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      repo
+        .save(sway.layout().fingerprint(), Saved::new(sway.layout()))
+        .unwrap();
+      repo.save(String::from(PINS), Pins::new()).unwrap();
+      std::fs::write(repo.path(String::from("junk")), "{").unwrap();
+      list_saved_layouts(repo, None, SystemTime::now()).unwrap()
+    });
+    assert_eq!(format!("{} 1 output", sway.layout().fingerprint()), actual);
+  }
+
+  #[test]
+  fn delete_should_forget_the_layout_saved_for_the_current_one() {
+    let sway = FakeSway::new();
+    let (deleted, again, exists) = with_store(|root| {
+      let repo = Repository::new(root.clone());
+      let path = repo.path(sway.layout().fingerprint());
+      silently_save_layout(repo, sway.layout(), &[], &[], None, &Options::default()).unwrap();
+      let delete = || {
+        run_once(
+          sway.clone(),
+          "test",
+          Repository::new(root.clone()),
+          Action::Delete,
+          &Options::default(),
+        )
+      };
+      (delete(), delete(), Path::new(&path).exists())
+    });
+    assert_eq!(String::new(), deleted.unwrap());
+    assert_eq!(
+      "no saved layout for this setup",
+      again.unwrap_err().to_string()
+    );
+    assert!(!exists);
+  }
+
+  #[test]
+  fn unparsable_fields_should_be_handed_over_as_warnings() {
+    let (warnings, options) = listening();
+    let data = OUTPUTS.replace("\"normal\"", "\"sideways\"");
+    assert!(parse_layout(data.as_bytes(), &options).is_ok());
+    assert_eq!(1, warnings.borrow().len());
+  }
+
+  #[test]
+  fn an_incompatible_saved_layout_should_be_ignored_unless_strict() {
+    let live = FakeSway::new().layout();
+    let saved: Layout = serde_json::from_str(&OUTPUTS.replace("Samsung", "Apple")).unwrap();
+    let strict = Options {
+      strict: true,
+      ..Options::default()
+    };
+    let (warnings, options) = listening();
+    let actual = merge_or_current(Some(Saved::new(saved.clone())), live.clone(), &options);
+    assert_eq!(live, actual.unwrap());
+    assert_eq!(1, warnings.borrow().len());
+    match merge_or_current(Some(Saved::new(saved)), live, &strict) {
+      Err(Error::Merge(MergeError(id))) => assert_eq!("Samsung|XYZ|12345", id),
+      _ => panic!("expected a merge error"),
+    }
+  }
+
+  const COMMAND: &str = "output eDP1 enable res 1920x1080 pos 0 0 transform normal";
+
+  const OUTPUTS: &str = r#"
+    [
+      {
+        "name": "eDP1",
+        "make": "Samsung",
+        "model": "XYZ",
+        "serial": "12345",
+        "transform": "normal",
+        "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+        "active": true
+      }
+    ]
+  "#;
+
+  const WORKSPACES: &str = r#"
+    [
+      {"name": "1", "focused": false, "output": "eDP1"},
+      {"name": "2: web", "focused": true, "output": "eDP1"}
+    ]
+  "#;
+
+  /// Answers like Sway would and records every command it receives.
+  #[derive(Clone)]
+  struct FakeSway {
+    outputs: String,
+    workspaces: String,
+    inputs: String,
+    tree: String,
+    received: Rc<RefCell<Vec<String>>>,
+    fetched: Rc<RefCell<Vec<u8>>>,
+    runs: Rc<Cell<usize>>,
+  }
+
+  impl FakeSway {
+    fn new() -> Self {
+      FakeSway {
+        outputs: String::from(OUTPUTS),
+        workspaces: String::from("[]"),
+        inputs: String::from("[]"),
+        tree: String::from("{}"),
+        received: Rc::new(RefCell::new(Vec::new())),
+        fetched: Rc::new(RefCell::new(Vec::new())),
+        runs: Rc::new(Cell::new(0)),
+      }
+    }
+
+    fn layout(&self) -> Layout {
+      serde_json::from_str(&self.outputs).unwrap()
+    }
+
+    fn commands(&self) -> Vec<String> {
+      self.received.borrow().clone()
+    }
+
+    /// How many queries of the given message type were received.
+    fn fetched(&self, kind: u8) -> usize {
+      self.fetched.borrow().iter().filter(|k| **k == kind).count()
+    }
+  }
+
+  impl Transport for FakeSway {
+    fn roundtrip(&mut self, bytes: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+      match bytes[4] {
+        0 => {
+          let payload = String::from_utf8(bytes[8..].to_vec()).unwrap();
+          let commands: Vec<String> = payload.split("; ").map(String::from).collect();
+          let responses = vec![r#"{"success": true}"#; commands.len()];
+          self.runs.set(self.runs.get() + 1);
+          self.received.borrow_mut().extend(commands);
+          Ok(format!("[{}]", responses.join(",")).into_bytes())
+        }
+        2 => Ok(br#"{"success": true}"#.to_vec()),
+        kind => {
+          self.fetched.borrow_mut().push(kind);
+          match kind {
+            1 => Ok(self.workspaces.as_bytes().to_vec()),
+            4 => Ok(self.tree.as_bytes().to_vec()),
+            100 => Ok(self.inputs.as_bytes().to_vec()),
+            _ => Ok(self.outputs.as_bytes().to_vec()),
+          }
+        }
+      }
+    }
+  }
+
+  /// Accepts a given number of commands then rejects the others. Unlike
+  /// FakeSway, it can't be cloned.
+  struct Rejecting(usize, Vec<String>);
+
+  impl Transport for Rejecting {
+    fn roundtrip(&mut self, bytes: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+      self.1.push(String::from_utf8(bytes[8..].to_vec()).unwrap());
+      match self.1.len() > self.0 {
+        true => Ok(br#"[{"success": false, "error": "rejected"}]"#.to_vec()),
+        false => Ok(br#"[{"success": true}]"#.to_vec()),
+      }
+    }
+  }
+
+  fn auto(sway: &FakeSway, root: String, options: Options) -> Result<String, Error> {
+    run_once(
+      sway.clone(),
+      "test",
+      Repository::new(root),
+      Action::Auto,
+      &options,
+    )
+  }
+
+  /// Powers a single output off once the delay elapsed on the clock, like
+  /// a power schedule would. It stays enabled, so that Sway keeps it in
+  /// the layout.
+  fn power_off_after<T>(
+    ipc: T,
+    layout: &Layout,
+    name: &str,
+    delay: Duration,
+    clock: &impl Clock,
+  ) -> Result<String, Error>
+  where
+    T: Transport,
+  {
+    if layout.command_for(name).is_none() {
+      return Err(Error::Output(format!("unknown output: {}", name)));
+    }
+    clock.sleep(delay);
+    let command = format!("output {} power off", name);
+    run_output_command((ipc, Message::RunCommand(command))).map(|_| String::new())
+  }
+
+  fn try_saved_disabled_layout(sway: &FakeSway, root: String, confirmed: bool) {
+    let repo = Repository::new(root);
+    let saved = Saved {
+      command_sequence: Some(vec![String::from("output eDP1 disable")]),
+      ..Saved::new(sway.layout())
+    };
+    repo.save(sway.layout().fingerprint(), saved).unwrap();
+    let confirmation = FakeConfirmation(confirmed);
+    let timeout = Duration::from_secs(10);
+    try_layout(
+      repo,
+      sway.clone(),
+      "test",
+      sway.layout(),
+      &Options::default(),
+      confirmation,
+      timeout,
+    )
+    .unwrap();
+  }
+
+  struct FakeConfirmation(bool);
+
+  impl Confirmation for FakeConfirmation {
+    fn wait(&mut self, _: Duration) -> bool {
+      self.0
+    }
+  }
+
+  /// Options collecting warnings rather than dropping them.
+  fn listening() -> (Rc<RefCell<Vec<String>>>, Options) {
+    let warnings = Rc::new(RefCell::new(Vec::new()));
+    let collected = warnings.clone();
+    let options = Options {
+      on_warning: Some(Rc::new(move |w: &str| {
+        collected.borrow_mut().push(w.to_string())
+      })),
+      ..Options::default()
+    };
+    (warnings, options)
+  }
+
+  impl Events for FakeSway {
+    fn poll(&mut self, _: Option<Duration>) -> watch::Poll {
+      watch::Poll::Closed
+    }
+  }
+
+  fn sockets(paths: &[&str]) -> Vec<String> {
+    paths.iter().map(|p| String::from(*p)).collect()
+  }
+
+  fn with_store<T, F>(test: F) -> T
+  where
+    F: FnOnce(String) -> T,
+  {
+    let dir = tempfile::tempdir().unwrap();
+    test(dir.path().to_str().unwrap().to_string())
+  }
+
+  /// Runs a test against a store holding the given outputs as the layout
+  /// saved for the live one.
+  fn with_saved<T, F>(sway: &FakeSway, saved: &str, test: F) -> T
+  where
+    F: FnOnce(&Repository) -> T,
+  {
+    with_store(|root| {
+      let repo = Repository::new(root);
+      let layout: Layout = serde_json::from_str(saved).unwrap();
+      repo
+        .save(sway.layout().fingerprint(), Saved::new(layout))
+        .unwrap();
+      test(&repo)
+    })
+  }
+
+  fn two_monitors() -> String {
+    let output = OUTPUTS.trim().trim_matches(|c| c == '[' || c == ']');
+    format!(
+      "[{},{}]",
+      output,
+      output
+        .replace("eDP1", "HDMI-A-1")
+        .replace("12345", "67890")
+        .replace("\"x\": 0", "\"x\": 1920")
+    )
+  }
+}
@@ -1,35 +1,305 @@
-use autosway::Action;
+use autosway::{Action, Config, FingerprintMode, Format, JsonStyle, Options, Profile, StoreFormat};
 use std::env;
+use std::fs;
+use std::process;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+
+const USAGE: &str = "usage: autosway [auto|save [<name>] [--record-sequence] [--verify] [--inputs]|apply <name>|list [--format text|env|json|--json [--json-compact|--json-pretty]]|rank|list-saved [--since <duration>]|watch [--debounce <ms>]|diff-default|map|try [--timeout <duration>]|confirm|normalize-store|delete|workspaces|dry-run|diff|fingerprint|info <fingerprint>|show <fingerprint>|import <path>|export <fingerprint> <path>|enable <connector>|disable <connector>|transform <connector> <degrees> [--save]] [--socket <path>...] [--store <dir>] [--reload-after] [--force] [--strict] [--apply-if-changed] [--reanchor] [--notify] [--max-resolution <px>] [--ipc-magic <string>] [--ipc-timeout <ms>] [--max-payload <bytes>] [--connect-attempts <n>] [--connect-delay <ms>] [--max-outputs <n>] [--self-test] [--apply-delay <ms>] [--one-by-one] [--origin <make|model|serial>] [--fragment <id>...] [--profile sway|wayfire|river] [--store-format json|toml|yaml] [--fingerprint strict|model-only] [-h|--help]";
 
 fn main() {
-  match autosway::run(
-    required_env("SWAYSOCK"),
-    required_env("AUTOSWAY"),
-    action_from(first_cli_argument()),
-  ) {
+  let args: Vec<String> = env::args().skip(1).collect();
+  if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+    println!("{}", USAGE);
+    return;
+  }
+  if args.iter().any(|arg| arg == "--self-test") {
+    return self_test();
+  }
+  let action = action_from(&args).unwrap_or_else(|usage| {
+    eprintln!("{}", usage);
+    process::exit(2);
+  });
+  let (sockets, store) = match (sockets_from(&args), store_from(&args)) {
+    (Ok(sockets), Ok(store)) => (sockets, store),
+    (Err(error), _) | (_, Err(error)) => {
+      eprintln!("error: {}", error);
+      process::exit(1);
+    }
+  };
+  let config = Config::load(&store).unwrap_or_else(|error| {
+    eprintln!("error: {}", error);
+    process::exit(1);
+  });
+  let options = options_from(&args, &config).unwrap_or_else(|usage| {
+    eprintln!("{}", usage);
+    process::exit(2);
+  });
+  match autosway::run(sockets, store, action, options) {
     Ok(ref output) if output.len() > 0 => println!("{}", output),
     Err(error) => eprintln!("error: {}", error),
     _ => (),
   }
 }
 
-/// Parses the action string to choose what to perform next.
-fn action_from(action: Option<String>) -> Action {
-  match action.as_ref() {
+/// Checks the IPC framing without Sway, exiting with an error status on
+/// failure.
+fn self_test() {
+  match autosway::self_test() {
+    Ok(output) => println!("{}", output),
+    Err(error) => {
+      eprintln!("error: {}", error);
+      process::exit(1);
+    }
+  }
+}
+
+/// Parses the action string to choose what to perform next. Fails with
+/// the usage to print if the action is unknown or incomplete.
+fn action_from(args: &[String]) -> Result<Action, String> {
+  let action = match args.first() {
     Some(arg) if arg == "auto" => Action::Auto,
-    Some(arg) if arg == "save" => Action::Save,
-    Some(arg) if arg == "list" => Action::List,
+    Some(arg) if arg == "save" => {
+      Action::Save(args.get(1).filter(|a| !a.starts_with('-')).cloned())
+    }
+    Some(arg) if arg == "apply" => match args.get(1).filter(|a| !a.starts_with('-')) {
+      Some(name) => Action::Apply(name.clone()),
+      None => return Err(String::from("usage: autosway apply <name>")),
+    },
+    Some(arg) if arg == "list" => Action::List(format_from(args)?),
+    Some(arg) if arg == "rank" => Action::Rank,
+    Some(arg) if arg == "watch" => Action::Watch,
+    Some(arg) if arg == "diff-default" => Action::Defaults,
+    Some(arg) if arg == "map" => Action::Map,
+    Some(arg) if arg == "try" => Action::Try(
+      flag_value(args, "--timeout")
+        .map(duration_from)
+        .transpose()?
+        .unwrap_or_else(|| Duration::from_secs(15)),
+    ),
+    Some(arg) if arg == "confirm" => Action::Confirm,
+    Some(arg) if arg == "normalize-store" => Action::Normalize,
+    Some(arg) if arg == "delete" => Action::Delete,
+    Some(arg) if arg == "workspaces" => Action::Workspaces,
+    Some(arg) if arg == "dry-run" => Action::DryRun,
+    Some(arg) if arg == "diff" => Action::Diff,
+    Some(arg) if arg == "fingerprint" => Action::Fingerprint,
+    Some(arg) if arg == "import" => match args.get(1) {
+      Some(path) => Action::Import(path.clone()),
+      None => return Err(String::from("usage: autosway import <path>")),
+    },
+    Some(arg) if arg == "export" => match (args.get(1), args.get(2)) {
+      (Some(id), Some(path)) => Action::Export(id.clone(), path.clone()),
+      _ => return Err(String::from("usage: autosway export <fingerprint> <path>")),
+    },
+    Some(arg) if arg == "info" || arg == "show" => {
+      match args.get(1).filter(|a| !a.starts_with('-')) {
+        Some(id) if arg == "info" => Action::Info(id.clone()),
+        Some(id) => Action::Show(id.clone()),
+        None => return Err(format!("usage: autosway {} <fingerprint>", arg)),
+      }
+    }
+    Some(arg) if arg == "disable" || arg == "enable" => match args.get(1) {
+      Some(name) if arg == "disable" => Action::Disable(name.clone()),
+      Some(name) => Action::Enable(name.clone()),
+      None => return Err(format!("usage: autosway {} <connector>", arg)),
+    },
+    Some(arg) if arg == "transform" => match (args.get(1), args.get(2)) {
+      (Some(name), Some(transform)) => Action::Transform(name.clone(), transform.clone()),
+      _ => {
+        return Err(String::from(
+          "usage: autosway transform <connector> <degrees> [--save]",
+        ))
+      }
+    },
+    Some(arg) if arg == "list-saved" => {
+      Action::Saved(flag_value(args, "--since").map(duration_from).transpose()?)
+    }
     None => Action::Auto,
-    _ => panic!("usage: autosway [auto|save|list]"),
+    _ => return Err(String::from(USAGE)),
+  };
+  Ok(action)
+}
+
+/// Chooses how layouts are printed, --json being short for --format json.
+fn format_from(args: &[String]) -> Result<Format, String> {
+  let json = args
+    .iter()
+    .any(|arg| arg == "--json")
+    .then(|| String::from("json"));
+  match flag_value(args, "--format").or(json) {
+    Some(ref format) if format == "env" => Ok(Format::Env),
+    Some(ref format) if format == "text" => Ok(Format::Text),
+    Some(ref format) if format == "json" => match args.iter().any(|arg| arg == "--json-compact") {
+      true => Ok(Format::Json(JsonStyle::Compact)),
+      false => Ok(Format::Json(JsonStyle::Pretty)),
+    },
+    None => Ok(Format::Text),
+    Some(format) => Err(format!("unknown format: {}", format)),
   }
 }
 
-/// The action to be performed, as string.
-fn first_cli_argument() -> Option<String> {
-  env::args().into_iter().skip(1).next()
+/// Collects the flags tuning how the action is performed, falling back
+/// to the configuration of the store for those not given. Fails with the
+/// usage error to print if a value is invalid.
+fn options_from(args: &[String], config: &Config) -> Result<Options, String> {
+  Ok(Options {
+    reload_after: args.iter().any(|arg| arg == "--reload-after"),
+    record_sequence: args.iter().any(|arg| arg == "--record-sequence"),
+    verify: args.iter().any(|arg| arg == "--verify"),
+    force: args.iter().any(|arg| arg == "--force"),
+    reanchor: args.iter().any(|arg| arg == "--reanchor"),
+    notify: args.iter().any(|arg| arg == "--notify"),
+    save: args.iter().any(|arg| arg == "--save"),
+    strict: args.iter().any(|arg| arg == "--strict"),
+    apply_if_changed: args.iter().any(|arg| arg == "--apply-if-changed"),
+    magic: flag_value(args, "--ipc-magic"),
+    ipc_timeout: parsed(args, "--ipc-timeout")?.map(Duration::from_millis),
+    max_payload: parsed(args, "--max-payload")?,
+    connect_attempts: parsed(args, "--connect-attempts")?.unwrap_or(1),
+    connect_delay: parsed(args, "--connect-delay")?
+      .map(Duration::from_millis)
+      .unwrap_or_else(|| Duration::from_millis(200)),
+    origin: flag_value(args, "--origin"),
+    fragments: flag_values(args, "--fragment"),
+    apply_delay: parsed(args, "--apply-delay")?
+      .map(Duration::from_millis)
+      .unwrap_or_default(),
+    one_by_one: args.iter().any(|arg| arg == "--one-by-one")
+      || config.combine_commands == Some(false),
+    inputs: args.iter().any(|arg| arg == "--inputs"),
+    max_outputs: parsed(args, "--max-outputs")?,
+    max_resolution: parsed(args, "--max-resolution")?,
+    store_format: named(
+      flag_value(args, "--store-format").or_else(|| config.store_format.clone()),
+      "store format",
+      StoreFormat::from_name,
+    )?,
+    fingerprint: named(
+      flag_value(args, "--fingerprint").or_else(|| config.fingerprint.clone()),
+      "fingerprint mode",
+      FingerprintMode::from_name,
+    )?,
+    profile: named(flag_value(args, "--profile"), "profile", Profile::from_name)?,
+    debounce: parsed(args, "--debounce")?
+      .map(Duration::from_millis)
+      .unwrap_or_default(),
+    on_warning: Some(Rc::new(|warning| eprintln!("warning: {}", warning))),
+  })
+}
+
+/// Parses the value given to a flag, if any.
+fn parsed<T: FromStr>(args: &[String], flag: &str) -> Result<Option<T>, String> {
+  flag_value(args, flag)
+    .map(|value| {
+      value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", flag, value))
+    })
+    .transpose()
+}
+
+/// Looks a variant up by name, if one is given, defaulting otherwise.
+fn named<T, F>(name: Option<String>, what: &str, from_name: F) -> Result<T, String>
+where
+  T: Default,
+  F: Fn(&str) -> Option<T>,
+{
+  match name {
+    Some(name) => from_name(&name).ok_or_else(|| format!("unknown {}: {}", what, name)),
+    None => Ok(T::default()),
+  }
+}
+
+/// Sway sockets given with --socket, repeated or comma-separated.
+/// Defaults to the one of the current session, found in the runtime
+/// directory if $SWAYSOCK is unset.
+fn sockets_from(args: &[String]) -> Result<Vec<String>, String> {
+  match flag_values(args, "--socket") {
+    ref paths if paths.is_empty() => {
+      layered("--socket", None, "SWAYSOCK", Some(String::new())).map(|p| vec![p])
+    }
+    paths => Ok(
+      paths
+        .iter()
+        .flat_map(|p| p.split(','))
+        .map(String::from)
+        .collect(),
+    ),
+  }
+}
+
+/// Where layouts are stored, given with --store or $AUTOSWAY. Defaults
+/// to the autosway folder of the user configuration.
+fn store_from(args: &[String]) -> Result<String, String> {
+  match flag_value(args, "--store").or_else(|| env::var("AUTOSWAY").ok()) {
+    Some(store) => Ok(store),
+    None => default_store(),
+  }
+}
+
+/// `$XDG_CONFIG_HOME/autosway`, or `~/.config/autosway`, created on first
+/// use.
+fn default_store() -> Result<String, String> {
+  let config = env::var("XDG_CONFIG_HOME")
+    .ok()
+    .or_else(|| {
+      env::var("HOME")
+        .ok()
+        .map(|home| format!("{}/.config", home))
+    })
+    .ok_or_else(|| {
+      String::from("--store was not given and none of $AUTOSWAY, $XDG_CONFIG_HOME or $HOME is set")
+    })?;
+  let store = format!("{}/autosway", config);
+  fs::create_dir_all(&store)
+    .map(|()| store.clone())
+    .map_err(|err| format!("could not create {}: {}", store, err))
+}
+
+/// Returns the argument following a given flag, if any.
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+  flag_values(args, flag).into_iter().next()
+}
+
+/// Returns the arguments following each occurrence of a given flag.
+fn flag_values(args: &[String], flag: &str) -> Vec<String> {
+  args
+    .windows(2)
+    .filter(|pair| pair[0] == flag)
+    .map(|pair| pair[1].clone())
+    .collect()
+}
+
+/// Parses durations such as 90s, 30m, 24h or 7d.
+fn duration_from(value: String) -> Result<Duration, String> {
+  let (amount, unit) = value.split_at(value.len().saturating_sub(1));
+  let seconds = match unit {
+    "s" => 1,
+    "m" => 60,
+    "h" => 60 * 60,
+    "d" => 24 * 60 * 60,
+    _ => return Err(format!("invalid duration unit: {}", value)),
+  };
+  amount
+    .parse::<u64>()
+    .ok()
+    .and_then(|n| n.checked_mul(seconds))
+    .map(Duration::from_secs)
+    .ok_or_else(|| format!("invalid duration: {}", value))
 }
 
-/// Panics if the environment variable is unset.
-fn required_env(name: &str) -> String {
-  env::var(name).expect(&format!("${} is unset.", name))
+/// Returns the value of a flag, else of the environment variable, else
+/// the default. Fails if none is set.
+fn layered(
+  flag: &str,
+  value: Option<String>,
+  name: &str,
+  default: Option<String>,
+) -> Result<String, String> {
+  value
+    .or_else(|| env::var(name).ok())
+    .or(default)
+    .ok_or_else(|| format!("{} was not given and ${} is unset", flag, name))
 }
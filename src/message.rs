@@ -4,7 +4,11 @@ use serde::Deserialize;
 #[derive(Debug)]
 pub enum Message {
   GetOutputs,
+  GetWorkspaces,
+  GetTree,
+  GetInputs,
   RunCommand(String),
+  Subscribe(Vec<String>),
 }
 
 impl Message {
@@ -19,7 +23,11 @@ impl Message {
   fn what(&self) -> u32 {
     match &self {
       Self::GetOutputs => 3,
+      Self::GetWorkspaces => 1,
+      Self::GetTree => 4,
+      Self::GetInputs => 100,
       Self::RunCommand(_) => 0,
+      Self::Subscribe(_) => 2,
     }
   }
 
@@ -27,15 +35,20 @@ impl Message {
   fn len(&self) -> u32 {
     match &self {
       Self::GetOutputs => 0,
+      Self::GetWorkspaces => 0,
+      Self::GetTree => 0,
+      Self::GetInputs => 0,
       Self::RunCommand(data) => data.len() as u32,
+      Self::Subscribe(_) => self.data().len() as u32,
     }
   }
 
   /// Returns the payload data.
   fn data(&self) -> Vec<u8> {
     match &self {
-      Self::GetOutputs => Vec::<u8>::new(),
+      Self::GetOutputs | Self::GetWorkspaces | Self::GetTree | Self::GetInputs => Vec::<u8>::new(),
       Self::RunCommand(data) => data.as_bytes().to_vec(),
+      Self::Subscribe(events) => serde_json::to_vec(events).unwrap(),
     }
   }
 }
@@ -44,16 +57,41 @@ impl Message {
 #[derive(Debug, Deserialize)]
 pub struct Response {
   pub success: bool,
+  /// Reason given by Sway when the command failed.
+  pub error: Option<String>,
+  /// Whether the command could not be parsed at all.
+  pub parse_error: Option<bool>,
 }
 
 impl Response {
-  /// Retuns true if all responses are successful.
-  pub fn bulk_scan(input: Vec<u8>) -> bool {
-    match serde_json::from_slice::<Vec<Self>>(&input) {
-      Ok(resp) => resp.iter().all(|r| r.success),
+  /// Returns true if the single response is successful.
+  pub fn scan(input: Vec<u8>) -> bool {
+    match serde_json::from_slice::<Self>(&input) {
+      Ok(resp) => resp.success,
       _ => false,
     }
   }
+
+  /// Ensures all responses are successful, yielding the error of the first
+  /// failing one otherwise. An empty array, as answered to some no-op
+  /// commands, is a success. Fails if the input isn't an array of
+  /// responses at all.
+  pub fn bulk_scan(input: Vec<u8>) -> Result<Result<(), Option<String>>, serde_json::Error> {
+    serde_json::from_slice::<Vec<Self>>(&input).map(|resp| {
+      match resp.into_iter().find(|r| !r.success) {
+        Some(failed) => Err(failed.reason()),
+        None => Ok(()),
+      }
+    })
+  }
+
+  /// Describes why the command failed, if Sway said so.
+  fn reason(self) -> Option<String> {
+    match (self.error, self.parse_error) {
+      (Some(error), Some(true)) => Some(format!("parse error: {}", error)),
+      (error, _) => error,
+    }
+  }
 }
 
 #[cfg(test)]
@@ -67,6 +105,27 @@ mod tests {
     assert_eq!(expected, actual);
   }
 
+  #[test]
+  fn it_should_serialize_a_get_workspaces_message() {
+    let expected = vec![0, 0, 0, 0, 1, 0, 0, 0];
+    let actual = super::Message::GetWorkspaces.to_bytes();
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn it_should_serialize_a_get_tree_message() {
+    let expected = vec![0, 0, 0, 0, 4, 0, 0, 0];
+    let actual = super::Message::GetTree.to_bytes();
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn it_should_serialize_a_get_inputs_message() {
+    let expected = vec![0, 0, 0, 0, 100, 0, 0, 0];
+    let actual = super::Message::GetInputs.to_bytes();
+    assert_eq!(expected, actual);
+  }
+
   #[test]
   fn it_should_serialize_a_run_command_message_with_a_payload() {
     let expected = vec![3, 0, 0, 0, 0, 0, 0, 0, 102, 111, 111];
@@ -74,6 +133,21 @@ mod tests {
     assert_eq!(expected, actual);
   }
 
+  #[test]
+  fn it_should_serialize_a_subscribe_message_with_a_json_payload() {
+    let expected = vec![
+      10, 0, 0, 0, 2, 0, 0, 0, 91, 34, 111, 117, 116, 112, 117, 116, 34, 93,
+    ];
+    let actual = super::Message::Subscribe(vec![String::from("output")]).to_bytes();
+    assert_eq!(expected, actual);
+  }
+
+  #[test]
+  fn it_should_scan_a_single_response() {
+    assert!(Response::scan(br#"{"success": true}"#.to_vec()));
+    assert!(!Response::scan(br#"{"success": false}"#.to_vec()));
+  }
+
   #[test]
   fn it_should_return_true_if_all_responses_are_successful() {
     let input = String::from(
@@ -87,11 +161,11 @@ mod tests {
     )
     .as_bytes()
     .to_vec();
-    assert!(Response::bulk_scan(input));
+    assert_eq!(Ok(()), Response::bulk_scan(input).unwrap());
   }
 
   #[test]
-  fn it_should_return_false_with_invalid_json() {
+  fn it_should_fail_with_invalid_json() {
     let input = String::from(
       r#"
       [
@@ -103,7 +177,7 @@ mod tests {
     )
     .as_bytes()
     .to_vec();
-    assert!(!Response::bulk_scan(input));
+    assert!(Response::bulk_scan(input).is_err());
   }
 
   #[test]
@@ -119,6 +193,35 @@ mod tests {
     )
     .as_bytes()
     .to_vec();
-    assert!(!Response::bulk_scan(input));
+    assert_eq!(Err(None), Response::bulk_scan(input).unwrap());
+  }
+
+  #[test]
+  fn it_should_return_true_if_there_is_no_response() {
+    assert_eq!(Ok(()), Response::bulk_scan(b"[]".to_vec()).unwrap());
+  }
+
+  #[test]
+  fn it_should_report_the_first_failing_command() {
+    let input = String::from(
+      r#"
+      [
+        {"success": true},
+        {"success": false, "parse_error": false, "error": "Unknown output DP-9"},
+        {"success": false, "parse_error": true, "error": "Unknown command"}
+      ]
+    "#,
+    )
+    .as_bytes()
+    .to_vec();
+    let expected = Err(Some(String::from("Unknown output DP-9")));
+    assert_eq!(expected, Response::bulk_scan(input).unwrap());
+  }
+
+  #[test]
+  fn it_should_flag_parse_errors() {
+    let input = br#"[{"success": false, "parse_error": true, "error": "Expected 'enable'"}]"#;
+    let expected = Err(Some(String::from("parse error: Expected 'enable'")));
+    assert_eq!(expected, Response::bulk_scan(input.to_vec()).unwrap());
   }
 }
@@ -0,0 +1,89 @@
+use serde_json::Value;
+
+/// Compositor reporting outputs, telling how its fields are named.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Profile {
+  #[default]
+  Sway,
+  Wayfire,
+  River,
+}
+
+impl Profile {
+  /// Looks up a profile by its command line name.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "sway" => Some(Profile::Sway),
+      "wayfire" => Some(Profile::Wayfire),
+      "river" => Some(Profile::River),
+      _ => None,
+    }
+  }
+
+  /// Renames the fields of each reported output to the ones Sway uses.
+  pub fn normalize(self, mut value: Value) -> Value {
+    if let Value::Array(ref mut outputs) = value {
+      for output in outputs.iter_mut().filter_map(Value::as_object_mut) {
+        for (from, to) in self.renames() {
+          if let Some(v) = output.remove(*from) {
+            output.insert(String::from(*to), v);
+          }
+        }
+      }
+    }
+    value
+  }
+
+  /// Pairs of field names as reported and as expected.
+  fn renames(self) -> &'static [(&'static str, &'static str)] {
+    match self {
+      Profile::Sway => &[],
+      Profile::Wayfire => &[
+        ("manufacturer", "make"),
+        ("serial_number", "serial"),
+        ("geometry", "rect"),
+        ("enabled", "active"),
+      ],
+      Profile::River => &[
+        ("output", "name"),
+        ("manufacturer", "make"),
+        ("enabled", "active"),
+      ],
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Profile;
+  use crate::layout::Layout;
+
+  #[test]
+  fn it_should_rename_fork_fields_before_deserializing() {
+    let value = serde_json::json!([{
+      "name": "eDP1",
+      "manufacturer": "foo",
+      "model": "bar",
+      "serial_number": "baz",
+      "geometry": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+      "enabled": true
+    }]);
+    let layout: Layout = serde_json::from_value(Profile::Wayfire.normalize(value)).unwrap();
+    assert_eq!(
+      vec!["output eDP1 enable res 1920x1080 pos 0 0 transform normal"],
+      layout.serialize_commands()
+    );
+  }
+
+  #[test]
+  fn it_should_leave_sway_fields_untouched() {
+    let value = serde_json::json!([{"manufacturer": "foo"}]);
+    assert_eq!(value.clone(), Profile::Sway.normalize(value));
+  }
+
+  #[test]
+  fn it_should_parse_profile_names() {
+    assert_eq!(Some(Profile::River), Profile::from_name("river"));
+    assert_eq!(None, Profile::from_name("weston"));
+  }
+}
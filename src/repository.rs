@@ -1,51 +1,321 @@
 use serde::de::DeserializeOwned;
-use serde::ser::Serialize;
+use serde::{Deserialize, Serialize};
 use std::error;
 use std::fmt;
 use std::fs;
 use std::io;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Folder holding the layouts saved under a name, apart from those keyed
+/// by fingerprint.
+const NAMED: &str = "named";
+
+/// How entities are written to files.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Format {
+  #[default]
+  Json,
+  Toml,
+  Yaml,
+}
+
+impl Format {
+  /// Looks up a format by its command line name.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "json" => Some(Format::Json),
+      "toml" => Some(Format::Toml),
+      "yaml" => Some(Format::Yaml),
+      _ => None,
+    }
+  }
+
+  /// Appended to identifiers to name files. JSON files have none, as they
+  /// were written before other formats existed.
+  fn extension(self) -> &'static str {
+    match self {
+      Format::Json => "",
+      Format::Toml => ".toml",
+      Format::Yaml => ".yaml",
+    }
+  }
+
+  /// Appended to names to name files, JSON ones included since they
+  /// never shared a folder with files lacking an extension.
+  fn named_extension(self) -> &'static str {
+    match self {
+      Format::Json => ".json",
+      other => other.extension(),
+    }
+  }
+}
 
 /// Responsible for saving and loading layouts to/from the filesystem.
-pub struct Repository(String);
+pub struct Repository(String, Format);
 
 impl Repository {
   /// Returns a new Repository that gets data from a given folder.
   pub fn new(fs_root: String) -> Self {
-    Repository(fs_root)
+    Repository(fs_root, Format::default())
+  }
+
+  /// Stores data in the given format rather than JSON.
+  pub fn in_format(self, format: Format) -> Self {
+    Repository(self.0, format)
   }
 
-  /// Writes a file containing layout data in JSON.
+  /// Writes a file containing layout data in canonical form.
   pub fn save<T>(&self, id: String, entity: T) -> Result<(), StorageError>
   where
     T: Serialize,
   {
-    serde_json::to_string(&entity)
-      .map_err(StorageError::Json)
-      .map(|data| fs::write(&self.path(id), data.as_bytes()))?
-      .map_err(StorageError::Io)
+    let path = self.file_path(id)?;
+    canonical(&entity, self.1).and_then(|data| write_atomically(&path, &data))
+  }
+
+  /// Writes an entity again unless the file already holds it in canonical
+  /// form, telling whether it was rewritten.
+  pub fn rewrite<T>(&self, id: String, entity: T) -> Result<bool, StorageError>
+  where
+    T: Serialize,
+  {
+    let path = self.file_path(id)?;
+    match canonical(&entity, self.1)? {
+      ref data if fs::read_to_string(&path).ok().as_ref() == Some(data) => Ok(false),
+      data => write_atomically(&path, &data).map(|_| true),
+    }
   }
 
-  /// Reads data into a given layout.
+  /// Reads data into a given layout. Empty files are reported as such
+  /// since they most likely result from an interrupted write.
   pub fn load<T>(&self, id: String) -> Result<T, StorageError>
   where
     T: DeserializeOwned,
   {
-    fs::File::open(&self.path(id))
-      .map_err(StorageError::Io)
-      .map(|fd| serde_json::from_reader(fd))?
-      .map_err(StorageError::Json)
+    read(self.file_path(id)?, self.1)
+  }
+
+  /// Writes an entity under a name rather than an identifier, so that it
+  /// never collides with layouts saved by fingerprint.
+  pub fn save_named<T>(&self, name: &str, entity: T) -> Result<(), StorageError>
+  where
+    T: Serialize,
+  {
+    let path = self.named_path(name)?;
+    fs::create_dir_all(Path::new(&self.0).join(NAMED))?;
+    canonical(&entity, self.1).and_then(|data| write_atomically(&path, &data))
+  }
+
+  /// Reads an entity saved under a name.
+  pub fn load_named<T>(&self, name: &str) -> Result<T, StorageError>
+  where
+    T: DeserializeOwned,
+  {
+    read(self.named_path(name)?, self.1)
+  }
+
+  /// Same as verify, for an entity saved under a name.
+  pub fn verify_named<T>(&self, name: &str, expected: &T) -> Result<(), StorageError>
+  where
+    T: DeserializeOwned + PartialEq,
+  {
+    match self.load_named::<T>(name)? == *expected {
+      true => Ok(()),
+      false => Err(StorageError::Mismatch(self.named_path(name)?)),
+    }
+  }
+
+  /// Removes a stored file, failing if there is none.
+  pub fn delete(&self, id: String) -> Result<(), StorageError> {
+    let path = self.file_path(id)?;
+    fs::remove_file(&path).map_err(StorageError::Io)
+  }
+
+  /// Reads data back and ensures it matches what was meant to be
+  /// written.
+  pub fn verify<T>(&self, id: String, expected: &T) -> Result<(), StorageError>
+  where
+    T: DeserializeOwned + PartialEq,
+  {
+    match self.load::<T>(id.clone())? == *expected {
+      true => Ok(()),
+      false => Err(StorageError::Mismatch(self.path(id))),
+    }
+  }
+
+  /// Reads every stored entity, skipping files that fail to load.
+  pub fn list<T>(&self) -> Result<Vec<(String, T)>, StorageError>
+  where
+    T: DeserializeOwned,
+  {
+    self.list_since(UNIX_EPOCH)
+  }
+
+  /// Reads every entity stored after a given time, skipping files that
+  /// fail to load.
+  pub fn list_since<T>(&self, threshold: SystemTime) -> Result<Vec<(String, T)>, StorageError>
+  where
+    T: DeserializeOwned,
+  {
+    Ok(
+      fs::read_dir(&self.0)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| modified_since(entry, threshold))
+        .filter_map(|entry| self.id_of(entry))
+        .filter_map(|id| self.load(id.clone()).ok().map(|entity| (id, entity)))
+        .collect(),
+    )
+  }
+
+  /// True once the folder holding the data was created.
+  pub fn exists(&self) -> bool {
+    Path::new(&self.0).is_dir()
+  }
+
+  /// True if something is stored under an identifier, without reading it.
+  pub fn contains(&self, id: String) -> bool {
+    Path::new(&self.path(id)).is_file()
+  }
+
+  /// Returns the identifier of every stored file, in order.
+  pub fn ids(&self) -> Result<Vec<String>, StorageError> {
+    let mut ids: Vec<String> = fs::read_dir(&self.0)?
+      .filter_map(|entry| entry.ok())
+      .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+      .filter_map(|entry| self.id_of(entry))
+      .collect();
+    ids.sort();
+    Ok(ids)
+  }
+
+  /// Returns the filepath for a given layout, unless a directory is found
+  /// there instead.
+  fn file_path(&self, id: String) -> Result<String, StorageError> {
+    match self.path(id) {
+      ref path if Path::new(path).is_dir() => Err(StorageError::Directory(path.clone())),
+      path => Ok(path),
+    }
   }
 
   /// Returns the filepath for a given layout.
   /// Panics if we can't build the path.
-  fn path(&self, id: String) -> String {
+  pub fn path(&self, id: String) -> String {
     Path::new(&self.0)
-      .join(id)
+      .join(id + self.1.extension())
       .to_str()
       .map(String::from)
       .unwrap()
   }
+
+  /// Returns the filepath for a given name, refusing names that could
+  /// lead out of the folder of named entities.
+  fn named_path(&self, name: &str) -> Result<String, StorageError> {
+    if name.is_empty() || name.contains('/') || name.contains("..") || name.contains('\0') {
+      return Err(StorageError::Invalid(
+        String::from(name),
+        String::from("names can't be empty nor contain '/', '..' or NUL"),
+      ));
+    }
+    Ok(
+      Path::new(&self.0)
+        .join(NAMED)
+        .join(String::from(name) + self.1.named_extension())
+        .to_str()
+        .map(String::from)
+        .unwrap(),
+    )
+  }
+
+  /// The identifier of an entry, if written in the configured format.
+  /// Hidden files, such as interrupted writes, have none.
+  fn id_of(&self, entry: fs::DirEntry) -> Option<String> {
+    let name = entry
+      .file_name()
+      .into_string()
+      .ok()
+      .filter(|name| !name.starts_with('.'))?;
+    name
+      .strip_suffix(self.1.extension())
+      .filter(|id| !id.is_empty())
+      .map(String::from)
+  }
+}
+
+/// Renders an entity the way every file is written: indented, with keys
+/// sorted so that files compare well under version control.
+fn canonical<T: Serialize>(entity: &T, format: Format) -> Result<String, StorageError> {
+  let value = serde_json::to_value(entity).map_err(StorageError::Json)?;
+  match format {
+    Format::Json => serde_json::to_string_pretty(&value).map_err(StorageError::Json),
+    Format::Toml => match toml::Value::try_from(&value) {
+      Ok(toml::Value::Table(table)) => toml::to_string_pretty(&table),
+      Ok(other) => toml::to_string_pretty(&Wrapped { value: other }),
+      Err(err) => Err(err),
+    }
+    .map_err(|err| StorageError::Syntax(err.to_string())),
+    Format::Yaml => {
+      serde_yaml_ng::to_string(&value).map_err(|err| StorageError::Syntax(err.to_string()))
+    }
+  }
+}
+
+/// Writes a file next to the destination then moves it into place, so
+/// that an interrupted write never leaves a truncated file behind.
+fn write_atomically(path: &str, data: &str) -> Result<(), StorageError> {
+  let path = Path::new(path);
+  let name = path
+    .file_name()
+    .and_then(|n| n.to_str())
+    .unwrap_or_default();
+  let tmp = path.with_file_name(format!(".{}.tmp", name));
+  fs::write(&tmp, data.as_bytes())
+    .and_then(|_| fs::rename(&tmp, path))
+    .map_err(|err| {
+      let _ = fs::remove_file(&tmp);
+      StorageError::Io(err)
+    })
+}
+
+/// Reads a file, reporting empty ones as such since they most likely
+/// result from an interrupted write.
+fn read<T: DeserializeOwned>(path: String, format: Format) -> Result<T, StorageError> {
+  match fs::read_to_string(&path)? {
+    ref data if data.trim().is_empty() => Err(StorageError::Empty(path)),
+    data => decode(&data, format),
+  }
+}
+
+/// Reads an entity written in the given format.
+fn decode<T: DeserializeOwned>(data: &str, format: Format) -> Result<T, StorageError> {
+  match format {
+    Format::Json => serde_json::from_str(data).map_err(StorageError::Json),
+    Format::Toml => toml::from_str(data)
+      .or_else(|err| {
+        toml::from_str::<Wrapped<T>>(data)
+          .map(|wrapped| wrapped.value)
+          .map_err(|_| err)
+      })
+      .map_err(|err| StorageError::Syntax(err.to_string())),
+    Format::Yaml => {
+      serde_yaml_ng::from_str(data).map_err(|err| StorageError::Syntax(err.to_string()))
+    }
+  }
+}
+
+/// An entity TOML can't store at the top of a file, such as an array,
+/// stored under a key instead.
+#[derive(Serialize, Deserialize)]
+struct Wrapped<T> {
+  value: T,
+}
+
+/// Returns true if the entry is a file modified after the threshold.
+fn modified_since(entry: &fs::DirEntry, threshold: SystemTime) -> bool {
+  match entry.metadata() {
+    Ok(ref meta) if meta.is_file() => meta.modified().map(|t| t >= threshold).unwrap_or(false),
+    _ => false,
+  }
 }
 
 #[derive(Debug)]
@@ -54,15 +324,44 @@ pub enum StorageError {
   Io(io::Error),
   /// Could not encode or decode to/from JSON.
   Json(serde_json::error::Error),
+  /// Data read back differs from what was written.
+  Mismatch(String),
+  /// The file exists but holds nothing.
+  Empty(String),
+  /// A directory stands where the file is expected.
+  Directory(String),
+  /// The file holds values that can't be applied.
+  Invalid(String, String),
+  /// Could not encode or decode to/from TOML or YAML.
+  Syntax(String),
 }
 
-impl error::Error for StorageError {}
+impl error::Error for StorageError {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match *self {
+      StorageError::Io(ref err) => Some(err),
+      StorageError::Json(ref err) => Some(err),
+      StorageError::Mismatch(_) => None,
+      StorageError::Empty(_) => None,
+      StorageError::Directory(_) => None,
+      StorageError::Invalid(_, _) => None,
+      StorageError::Syntax(_) => None,
+    }
+  }
+}
 
 impl fmt::Display for StorageError {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match *self {
       StorageError::Io(ref err) => write!(f, "storage: io: {}", err),
       StorageError::Json(ref err) => write!(f, "storage: json: {}", err),
+      StorageError::Empty(ref path) => write!(f, "storage: {} is empty or corrupt", path),
+      StorageError::Directory(ref path) => write!(f, "storage: {} is a directory", path),
+      StorageError::Invalid(ref path, ref reason) => {
+        write!(f, "storage: {} is invalid: {}", path, reason)
+      }
+      StorageError::Syntax(ref reason) => write!(f, "storage: {}", reason),
+      StorageError::Mismatch(ref path) => write!(f, "storage: {} was not written correctly", path),
     }
   }
 }
@@ -83,6 +382,7 @@ impl From<serde_json::error::Error> for StorageError {
 mod tests {
   use super::*;
   use std::panic;
+  use std::time::Duration;
 
   fn with_tmp_dir<T>(test: T) -> ()
   where
@@ -104,6 +404,76 @@ mod tests {
     });
   }
 
+  #[test]
+  fn it_should_save_objects_indented_with_sorted_keys() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root);
+      sut
+        .save(String::from("sut"), serde_json::json!({"b": 1, "a": 2}))
+        .unwrap();
+      let actual = fs::read_to_string(Path::new(&path)).unwrap();
+      assert_eq!("{\n  \"a\": 2,\n  \"b\": 1\n}", actual);
+    });
+  }
+
+  #[test]
+  fn it_should_load_compact_and_indented_json_alike() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root);
+      fs::write(Path::new(&path), r#"{"a":[1,2]}"#).unwrap();
+      let compact: serde_json::Value = sut.load(String::from("sut")).unwrap();
+      fs::write(Path::new(&path), "{\n  \"a\": [\n    1,\n    2\n  ]\n}").unwrap();
+      let indented: serde_json::Value = sut.load(String::from("sut")).unwrap();
+      assert_eq!(serde_json::json!({"a": [1, 2]}), compact);
+      assert_eq!(compact, indented);
+    });
+  }
+
+  #[test]
+  fn it_should_only_rewrite_files_not_in_canonical_form() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root);
+      fs::write(Path::new(&path), "[1,2]").unwrap();
+      assert!(sut.rewrite(String::from("sut"), vec![1, 2]).unwrap());
+      let canonical = fs::read_to_string(Path::new(&path)).unwrap();
+      assert!(!sut.rewrite(String::from("sut"), vec![1, 2]).unwrap());
+      assert_eq!(canonical, fs::read_to_string(Path::new(&path)).unwrap());
+    });
+  }
+
+  #[test]
+  fn layouts_should_roundtrip_in_every_format() {
+    let layout: crate::layout::Layout = serde_json::from_str(
+      r#"[{"name": "eDP1", "make": "Samsung", "model": "XYZ", "serial": "12345",
+        "transform": "normal", "active": true, "scale": 1.5,
+        "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080}},
+        {"name": "HDMI-2", "make": "Dell", "model": "U2414H", "serial": "67890",
+        "transform": null, "active": false,
+        "rect": {"x": 0, "y": 0, "width": 0, "height": 0}}]"#,
+    )
+    .unwrap();
+    for format in &[Format::Json, Format::Toml, Format::Yaml] {
+      with_tmp_dir(|root| {
+        let sut = Repository::new(root).in_format(*format);
+        sut.save(String::from("sut"), &layout).unwrap();
+        assert_eq!(layout, sut.load(String::from("sut")).unwrap());
+        assert_eq!(vec![String::from("sut")], sut.ids().unwrap());
+      });
+    }
+  }
+
+  #[test]
+  fn files_should_be_named_after_their_format() {
+    with_tmp_dir(|root| {
+      let sut = Repository::new(root.clone()).in_format(Format::Yaml);
+      sut.save(String::from("pins"), 42).unwrap();
+      assert!(Path::new(&root).join("pins.yaml").exists());
+      assert!(Repository::new(root)
+        .load::<u32>(String::from("pins"))
+        .is_err());
+    });
+  }
+
   #[test]
   fn it_should_retrieve_data_from_a_json_file() {
     with_tmp_dir(|root| {
@@ -114,6 +484,158 @@ mod tests {
     });
   }
 
+  #[test]
+  fn saving_should_replace_an_existing_file_entirely() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root.clone());
+      fs::write(Path::new(&path), "[1, 2, 3, 4, 5, 6, 7, 8, 9]").unwrap();
+      sut.save(String::from("sut"), vec![1]).unwrap();
+      assert_eq!("[\n  1\n]", fs::read_to_string(Path::new(&path)).unwrap());
+      assert_eq!(vec![String::from("sut")], sut.ids().unwrap());
+      assert!(!Path::new(&root).join(".sut.tmp").exists());
+    });
+  }
+
+  #[test]
+  fn interrupted_writes_should_not_be_listed() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root.clone());
+      fs::write(Path::new(&path), "1").unwrap();
+      fs::write(Path::new(&root).join(".sut.tmp"), "[").unwrap();
+      assert_eq!(vec![String::from("sut")], sut.ids().unwrap());
+    });
+  }
+
+  #[test]
+  fn named_entities_should_not_collide_with_identified_ones() {
+    with_tmp_dir(|root| {
+      let sut = Repository::new(root.clone());
+      sut.save(String::from("desk"), 1).unwrap();
+      sut.save_named("desk", 2).unwrap();
+      for name in &["../desk", "a/b", "", "a\0b"] {
+        assert!(sut.save_named(name, 2).is_err());
+        assert!(sut.load_named::<u32>(name).is_err());
+      }
+      assert!(Path::new(&root).join("named").join("desk.json").is_file());
+      assert_eq!(1, sut.load::<u32>(String::from("desk")).unwrap());
+      assert_eq!(2, sut.load_named::<u32>("desk").unwrap());
+      assert_eq!(vec![String::from("desk")], sut.ids().unwrap());
+    });
+  }
+
+  #[test]
+  fn it_should_list_entities_and_skip_unreadable_files() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root.clone());
+      fs::write(Path::new(&path), String::from("42").as_bytes()).unwrap();
+      fs::write(Path::new(&root).join("junk"), "{").unwrap();
+      let actual: Vec<(String, u32)> = sut.list().unwrap();
+      assert_eq!(vec![(String::from("sut"), 42)], actual);
+    });
+  }
+
+  #[test]
+  fn it_should_only_list_entities_modified_since_the_threshold() {
+    with_tmp_dir(|root| {
+      let sut = Repository::new(root.clone());
+      let day = Duration::from_secs(24 * 60 * 60);
+      fs::write(Path::new(&root).join("recent"), "1").unwrap();
+      fs::write(Path::new(&root).join("old"), "2").unwrap();
+      fs::File::options()
+        .write(true)
+        .open(Path::new(&root).join("old"))
+        .and_then(|fd| fd.set_modified(SystemTime::now() - 10 * day))
+        .unwrap();
+      let actual: Vec<(String, u32)> = sut.list_since(SystemTime::now() - 7 * day).unwrap();
+      assert_eq!(vec![(String::from("recent"), 1)], actual);
+    });
+  }
+
+  #[test]
+  fn it_should_delete_a_stored_file() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root);
+      sut.save(String::from("sut"), 42).unwrap();
+      sut.delete(String::from("sut")).unwrap();
+      assert!(!Path::new(&path).exists());
+      match sut.delete(String::from("sut")) {
+        Err(StorageError::Io(ref err)) => assert_eq!(io::ErrorKind::NotFound, err.kind()),
+        _ => panic!("expected an io error"),
+      }
+    });
+  }
+
+  #[test]
+  fn it_should_verify_a_successful_save() {
+    with_tmp_dir(|root| {
+      let (sut, _) = make_sut(root);
+      sut.save(String::from("sut"), 42).unwrap();
+      assert!(sut.verify(String::from("sut"), &42).is_ok());
+    });
+  }
+
+  #[test]
+  fn it_should_detect_a_corrupt_write() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root);
+      fs::write(Path::new(&path), String::from("4").as_bytes()).unwrap();
+      match sut.verify(String::from("sut"), &42) {
+        Err(StorageError::Mismatch(p)) => assert_eq!(path, p),
+        _ => panic!("expected a mismatch"),
+      }
+      fs::write(Path::new(&path), String::from("[4").as_bytes()).unwrap();
+      assert!(sut.verify(String::from("sut"), &vec![42]).is_err());
+    });
+  }
+
+  #[test]
+  fn it_should_report_an_empty_file() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root);
+      fs::write(Path::new(&path), "").unwrap();
+      match sut.load::<u32>(String::from("sut")) {
+        Err(StorageError::Empty(p)) => assert_eq!(path, p),
+        _ => panic!("expected an empty file error"),
+      }
+    });
+  }
+
+  #[test]
+  fn it_should_report_a_whitespace_only_file() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root);
+      fs::write(Path::new(&path), " \n\t\n").unwrap();
+      match sut.load::<u32>(String::from("sut")) {
+        Err(StorageError::Empty(_)) => (),
+        _ => panic!("expected an empty file error"),
+      }
+    });
+  }
+
+  #[test]
+  fn it_should_report_a_directory_in_place_of_a_file() {
+    with_tmp_dir(|root| {
+      let (sut, path) = make_sut(root);
+      fs::create_dir(Path::new(&path)).unwrap();
+      match sut.load::<u32>(String::from("sut")) {
+        Err(StorageError::Directory(p)) => assert_eq!(path, p),
+        _ => panic!("expected a directory error"),
+      }
+      match sut.save(String::from("sut"), 42) {
+        Err(StorageError::Directory(p)) => assert_eq!(path, p),
+        _ => panic!("expected a directory error"),
+      }
+    });
+  }
+
+  #[test]
+  fn it_should_expose_the_underlying_error() {
+    use std::error::Error;
+    let err = StorageError::Io(io::Error::from(io::ErrorKind::NotFound));
+    assert!(err.source().unwrap().is::<io::Error>());
+    assert!(StorageError::Empty(String::new()).source().is_none());
+  }
+
   fn make_sut(root: String) -> (Repository, String) {
     (
       Repository::new(root.clone()),
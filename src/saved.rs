@@ -0,0 +1,211 @@
+use crate::input::Input;
+use crate::layout::{Layout, MergePolicy};
+use crate::workspace::Assignments;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Files holding the name of the machine, most reliable first.
+const HOSTNAME_FILES: &[&str] = &["/proc/sys/kernel/hostname", "/etc/hostname"];
+
+/// Version of the schema layouts are saved with, bumped whenever the
+/// shape of saved files changes.
+const VERSION: u32 = 1;
+
+/// A layout as persisted, along with how to restore it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Saved {
+  /// Schema the layout was saved with, missing from files predating
+  /// versioning.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub version: Option<u32>,
+  /// The recorded outputs.
+  pub layout: Layout,
+  /// Commands to replay verbatim instead of deriving them from the
+  /// layout, for hardware with ordering quirks.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub command_sequence: Option<Vec<String>>,
+  /// How the layout is merged over the live one, overriding everything
+  /// when unset.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub merge_policy: Option<MergePolicy>,
+  /// When the layout was captured, as an RFC 3339 UTC timestamp.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub saved_at: Option<String>,
+  /// The machine the layout was captured on.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub hostname: Option<String>,
+  /// Which monitor each workspace was on.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub workspaces: Option<Assignments>,
+  /// Fingerprint of the layout, for those saved under a name.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub fingerprint: Option<String>,
+  /// Input devices restored along with the layout, for those saved with
+  /// them.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub inputs: Option<Vec<Input>>,
+}
+
+impl Saved {
+  /// Wraps a layout without any extra data.
+  pub fn new(layout: Layout) -> Self {
+    Saved {
+      layout,
+      ..Saved::default()
+    }
+    .upgraded()
+  }
+
+  /// Marks the layout as following the current schema, which it does
+  /// once read into memory.
+  pub fn upgraded(self) -> Self {
+    Saved {
+      version: Some(VERSION),
+      ..self
+    }
+  }
+
+  /// Records when and where the layout is being saved.
+  pub fn stamped(self, now: SystemTime) -> Self {
+    Saved {
+      saved_at: Some(rfc3339(now)),
+      hostname: hostname(),
+      ..self
+    }
+  }
+
+  /// Describes when and where the layout was saved, if known.
+  pub fn provenance(&self) -> Option<String> {
+    match (&self.saved_at, &self.hostname) {
+      (Some(at), Some(host)) => Some(format!("saved {} on {}", at, host)),
+      (Some(at), None) => Some(format!("saved {}", at)),
+      (None, Some(host)) => Some(format!("saved on {}", host)),
+      (None, None) => None,
+    }
+  }
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp, to the second.
+fn rfc3339(time: SystemTime) -> String {
+  let secs = time
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0);
+  let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+  // Civil date from days since the epoch, in 400-year eras of March
+  // based years so that leap days fall at the end.
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097);
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+  format!(
+    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+    year,
+    month,
+    day,
+    rem / 3600,
+    rem % 3600 / 60,
+    rem % 60
+  )
+}
+
+/// The name of this machine, if it can be found.
+fn hostname() -> Option<String> {
+  HOSTNAME_FILES
+    .iter()
+    .filter_map(|path| fs::read_to_string(path).ok())
+    .map(|name| String::from(name.trim()))
+    .find(|name| !name.is_empty())
+}
+
+/// What can be found on disk: files written before layouts were wrapped
+/// only contain the outputs.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Stored {
+  Envelope(Saved),
+  Bare(Layout),
+}
+
+impl From<Stored> for Saved {
+  fn from(stored: Stored) -> Self {
+    match stored {
+      Stored::Envelope(saved) => saved,
+      Stored::Bare(layout) => Saved::new(layout),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_should_read_bare_layouts() {
+    let input = r#"[{
+      "name": "eDP1",
+      "make": "Samsung",
+      "model": "XYZ",
+      "serial": "12345",
+      "transform": null,
+      "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+      "active": true
+    }]"#;
+    let actual = Saved::from(serde_json::from_str::<Stored>(input).unwrap());
+    assert_eq!(1, actual.layout.serialize_commands().len());
+    assert_eq!(None, actual.command_sequence);
+  }
+
+  #[test]
+  fn it_should_read_wrapped_layouts() {
+    let input = r#"{"layout": [], "command_sequence": ["output eDP1 disable"]}"#;
+    let actual = Saved::from(serde_json::from_str::<Stored>(input).unwrap());
+    assert_eq!(
+      Some(vec![String::from("output eDP1 disable")]),
+      actual.command_sequence
+    );
+  }
+
+  #[test]
+  fn it_should_format_timestamps_as_rfc3339() {
+    let time = UNIX_EPOCH + std::time::Duration::from_secs(951_827_696);
+    assert_eq!("2000-02-29T12:34:56Z", rfc3339(time));
+    assert_eq!("1970-01-01T00:00:00Z", rfc3339(UNIX_EPOCH));
+  }
+
+  #[test]
+  fn it_should_describe_where_a_layout_was_saved() {
+    let saved = Saved {
+      saved_at: Some(String::from("2000-02-29T12:34:56Z")),
+      hostname: Some(String::from("desk")),
+      ..Saved::default()
+    };
+    assert_eq!(
+      Some(String::from("saved 2000-02-29T12:34:56Z on desk")),
+      saved.provenance()
+    );
+    assert_eq!(None, Saved::default().provenance());
+  }
+
+  #[test]
+  fn it_should_version_new_and_upgraded_layouts() {
+    let input = r#"{"layout": []}"#;
+    let actual = Saved::from(serde_json::from_str::<Stored>(input).unwrap());
+    assert_eq!(None, actual.version);
+    assert_eq!(Some(VERSION), actual.upgraded().version);
+    assert_eq!(Some(VERSION), Saved::new(Layout::default()).version);
+  }
+
+  #[test]
+  fn it_should_read_the_merge_policy() {
+    let input = r#"{"layout": [], "merge_policy": "keep-positions"}"#;
+    let actual = Saved::from(serde_json::from_str::<Stored>(input).unwrap());
+    assert_eq!(Some(MergePolicy::KeepPositions), actual.merge_policy);
+  }
+}
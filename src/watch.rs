@@ -0,0 +1,260 @@
+use crate::clock::Clock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// How often the shutdown flag is checked while no event is pending.
+const SHUTDOWN_CHECK: Duration = Duration::from_millis(250);
+
+/// What happened while waiting for Sway to report a change.
+#[derive(Debug, PartialEq)]
+pub enum Poll {
+  /// Outputs changed.
+  Event,
+  /// Nothing happened before the timeout elapsed.
+  Timeout,
+  /// The event source is gone.
+  Closed,
+}
+
+/// Reports output changes as they happen.
+pub trait Events {
+  /// Blocks until the next event, or until the timeout elapses if any.
+  fn poll(&mut self, timeout: Option<Duration>) -> Poll;
+}
+
+/// Calls apply once per burst of events, after the debounce window
+/// elapsed on the clock without any new event. A zero window applies as
+/// soon as the events already queued are consumed, which also coalesces
+/// the events received while applying into a single follow-up call.
+/// Returns when the event source is closed or shutdown is requested,
+/// telling which.
+pub fn watch<E, F>(
+  events: &mut E,
+  debounce: Duration,
+  shutdown: &AtomicBool,
+  clock: &impl Clock,
+  mut apply: F,
+) -> Stop
+where
+  E: Events,
+  F: FnMut(),
+{
+  let mut deadline = None;
+  loop {
+    if shutdown.load(Ordering::SeqCst) {
+      return Stop::Shutdown;
+    }
+    match (events.poll(timeout(deadline, clock)), deadline) {
+      (Poll::Event, _) => deadline = Some(clock.now() + debounce),
+      (Poll::Timeout, Some(at)) if clock.now() >= at => {
+        apply();
+        deadline = None;
+      }
+      (Poll::Timeout, _) => (),
+      (Poll::Closed, _) => return Stop::Closed,
+    }
+  }
+}
+
+/// Why watching stopped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stop {
+  /// The event source is gone.
+  Closed,
+  /// Shutdown was requested, by a signal for instance.
+  Shutdown,
+}
+
+/// What watch mode left behind when it stopped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct State {
+  /// Fingerprint of the layout last applied, if any was.
+  pub last_layout: Option<String>,
+  pub stopped: Stop,
+}
+
+/// Wait for a first event, checking for shutdown from time to time, then
+/// for what is left of the debounce window.
+fn timeout(deadline: Option<SystemTime>, clock: &impl Clock) -> Option<Duration> {
+  match deadline {
+    Some(at) => Some(at.duration_since(clock.now()).unwrap_or_default()),
+    None => Some(SHUTDOWN_CHECK),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::clock::Fake;
+  use std::time::UNIX_EPOCH;
+
+  #[test]
+  fn events_within_the_window_should_trigger_a_single_apply() {
+    let (mut events, clock) = make_events(vec![0, 10, 20]);
+    let mut count = 0;
+    watch(
+      &mut events,
+      Duration::from_millis(100),
+      &running(),
+      &clock,
+      || count += 1,
+    );
+    assert_eq!(1, count);
+  }
+
+  #[test]
+  fn events_beyond_the_window_should_trigger_multiple_applies() {
+    let (mut events, clock) = make_events(vec![0, 200, 400]);
+    let mut count = 0;
+    watch(
+      &mut events,
+      Duration::from_millis(100),
+      &running(),
+      &clock,
+      || count += 1,
+    );
+    assert_eq!(3, count);
+  }
+
+  #[test]
+  fn a_zero_window_should_apply_on_every_event() {
+    let (mut events, clock) = make_events(vec![0, 10, 20]);
+    let mut count = 0;
+    watch(
+      &mut events,
+      Duration::from_millis(0),
+      &running(),
+      &clock,
+      || count += 1,
+    );
+    assert_eq!(3, count);
+  }
+
+  #[test]
+  fn events_received_while_applying_should_be_coalesced() {
+    let (mut events, clock) = make_events(vec![0, 150, 160, 170]);
+    let mut count = 0;
+    watch(
+      &mut events,
+      Duration::from_millis(0),
+      &running(),
+      &clock,
+      || {
+        count += 1;
+        clock.advance(Duration::from_millis(500));
+      },
+    );
+    assert_eq!(2, count);
+  }
+
+  #[test]
+  fn a_shutdown_request_should_stop_watching() {
+    let (mut events, clock) = make_events(vec![0, 200, 400]);
+    let shutdown = running();
+    let mut count = 0;
+    let stop = watch(
+      &mut events,
+      Duration::from_millis(100),
+      &shutdown,
+      &clock,
+      || {
+        count += 1;
+        shutdown.store(true, Ordering::SeqCst);
+      },
+    );
+    assert_eq!(Stop::Shutdown, stop);
+    assert_eq!(1, count);
+  }
+
+  #[test]
+  fn a_shutdown_requested_while_idle_should_stop_without_applying() {
+    let (mut events, clock) = make_events(vec![1000]);
+    let shutdown = AtomicBool::new(true);
+    let mut count = 0;
+    let stop = watch(
+      &mut events,
+      Duration::from_millis(100),
+      &shutdown,
+      &clock,
+      || count += 1,
+    );
+    assert_eq!(Stop::Shutdown, stop);
+    assert_eq!(0, count);
+  }
+
+  #[test]
+  fn a_closed_source_should_stop_watching() {
+    let (mut events, clock) = make_events(vec![0]);
+    let stop = watch(
+      &mut events,
+      Duration::from_millis(100),
+      &running(),
+      &clock,
+      || (),
+    );
+    assert_eq!(Stop::Closed, stop);
+  }
+
+  fn running() -> AtomicBool {
+    AtomicBool::new(false)
+  }
+
+  /// Replays events scheduled on a virtual timeline, in milliseconds
+  /// since the start of the clock. The source closes once the window
+  /// following the last event elapsed.
+  struct FakeEvents {
+    scheduled: Vec<u64>,
+    clock: Fake,
+    draining: bool,
+  }
+
+  impl Events for FakeEvents {
+    fn poll(&mut self, timeout: Option<Duration>) -> Poll {
+      let now = millis(self.clock.now());
+      match (self.scheduled.first().cloned(), timeout) {
+        (Some(at), Some(t)) if at <= now + t.as_millis() as u64 => self.fire(at),
+        (Some(at), None) => self.fire(at),
+        (Some(_), Some(t)) => {
+          self.clock.advance(t);
+          Poll::Timeout
+        }
+        (None, Some(t)) if self.draining => {
+          self.draining = false;
+          self.clock.advance(t);
+          Poll::Timeout
+        }
+        (None, _) => Poll::Closed,
+      }
+    }
+  }
+
+  impl FakeEvents {
+    fn fire(&mut self, at: u64) -> Poll {
+      self.scheduled.remove(0);
+      self.draining = true;
+      let now = millis(self.clock.now());
+      self
+        .clock
+        .advance(Duration::from_millis(at.saturating_sub(now)));
+      Poll::Event
+    }
+  }
+
+  fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+  }
+
+  fn make_events(scheduled: Vec<u64>) -> (FakeEvents, Fake) {
+    let clock = Fake::at(UNIX_EPOCH);
+    (
+      FakeEvents {
+        scheduled,
+        clock: clock.clone(),
+        draining: false,
+      },
+      clock,
+    )
+  }
+}
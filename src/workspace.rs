@@ -0,0 +1,202 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// The monitor each workspace is on, by workspace name and OEM
+/// identifier of the monitor.
+pub type Assignments = BTreeMap<String, String>;
+
+/// A workspace as reported by Sway.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Workspace {
+  pub name: String,
+  #[serde(default)]
+  pub focused: bool,
+  #[serde(default)]
+  pub output: String,
+}
+
+/// Name of the workspace having focus, if any.
+pub fn focused(workspaces: &[Workspace]) -> Option<String> {
+  workspaces
+    .iter()
+    .find(|w| w.focused)
+    .map(|w| w.name.clone())
+}
+
+/// The command bringing focus back to a workspace.
+pub fn focus_command(name: &str) -> String {
+  format!("workspace {}", quoted(name))
+}
+
+/// Lists workspaces under the output they are on, marking the focused
+/// one, outputs sorted by name.
+pub fn by_output(workspaces: &[Workspace]) -> String {
+  let mut outputs: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+  for w in workspaces {
+    outputs.entry(&w.output).or_default().push(match w.focused {
+      true => format!("  {} (focused)", w.name),
+      false => format!("  {}", w.name),
+    });
+  }
+  outputs
+    .iter()
+    .map(|(output, names)| format!("{}\n{}", output, names.join("\n")))
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+/// Records which monitor each workspace is on, given the connector name
+/// of each monitor by OEM identifier.
+pub fn assignments(workspaces: &[Workspace], connectors: &HashMap<String, String>) -> Assignments {
+  workspaces
+    .iter()
+    .filter_map(|w| {
+      connectors
+        .iter()
+        .find(|(_, name)| **name == w.output)
+        .map(|(id, _)| (w.name.clone(), id.clone()))
+    })
+    .collect()
+}
+
+/// Commands assigning workspaces back to the monitors they were on. Only
+/// workspaces currently present are assigned, as Sway would create the
+/// others empty. Those on another monitor are moved too, as assigning
+/// only applies to workspaces created afterwards; this focuses them, so
+/// focus is to be restored after.
+pub fn assignment_commands(
+  saved: &Assignments,
+  live: &[Workspace],
+  connectors: &HashMap<String, String>,
+) -> Vec<String> {
+  saved
+    .iter()
+    .filter_map(|(name, id)| {
+      live
+        .iter()
+        .find(|w| w.name == *name)
+        .zip(connectors.get(id))
+    })
+    .flat_map(|(w, output)| {
+      let assign = format!("workspace {} output {}", quoted(&w.name), output);
+      match w.output == *output {
+        true => vec![assign],
+        false => vec![assign, move_command(&w.name, output)],
+      }
+    })
+    .collect()
+}
+
+/// The command moving a workspace to a given monitor.
+fn move_command(name: &str, output: &str) -> String {
+  format!(
+    "{}; move workspace to output {}",
+    focus_command(name),
+    output
+  )
+}
+
+/// Quotes a workspace name for use in a command.
+fn quoted(name: &str) -> String {
+  format!("\"{}\"", name.replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_should_find_the_focused_workspace() {
+    let input = r#"[
+      {"name": "1", "focused": false, "output": "eDP1"},
+      {"name": "2: web", "focused": true, "output": "HDMI-2"}
+    ]"#;
+    let workspaces: Vec<Workspace> = serde_json::from_str(input).unwrap();
+    assert_eq!(Some(String::from("2: web")), focused(&workspaces));
+    assert_eq!(None, focused(&workspaces[..1]));
+  }
+
+  #[test]
+  fn only_existing_workspaces_should_be_assigned() {
+    let connectors: HashMap<String, String> = vec![
+      (String::from("A"), String::from("DP-1")),
+      (String::from("B"), String::from("DP-2")),
+    ]
+    .into_iter()
+    .collect();
+    let saved: Assignments = vec![
+      (String::from("1"), String::from("A")),
+      (String::from("2: web"), String::from("B")),
+      (String::from("3"), String::from("A")),
+    ]
+    .into_iter()
+    .collect();
+    let live: Vec<Workspace> = serde_json::from_str(
+      r#"[{"name": "1", "output": "DP-1"}, {"name": "2: web", "output": "DP-2"}]"#,
+    )
+    .unwrap();
+    assert_eq!(
+      vec![
+        r#"workspace "1" output DP-1"#,
+        r#"workspace "2: web" output DP-2"#
+      ],
+      assignment_commands(&saved, &live, &connectors)
+    );
+    assert!(assignment_commands(&saved, &[], &connectors).is_empty());
+  }
+
+  #[test]
+  fn workspaces_on_another_monitor_should_be_moved_back() {
+    let connectors: HashMap<String, String> = vec![(String::from("A"), String::from("DP-1"))]
+      .into_iter()
+      .collect();
+    let saved: Assignments = vec![(String::from("1"), String::from("A"))]
+      .into_iter()
+      .collect();
+    let live: Vec<Workspace> =
+      serde_json::from_str(r#"[{"name": "1", "output": "eDP1"}]"#).unwrap();
+    assert_eq!(
+      vec![
+        r#"workspace "1" output DP-1"#,
+        r#"workspace "1"; move workspace to output DP-1"#
+      ],
+      assignment_commands(&saved, &live, &connectors)
+    );
+  }
+
+  #[test]
+  fn it_should_record_the_monitor_of_each_workspace() {
+    let connectors: HashMap<String, String> = vec![(String::from("A"), String::from("eDP1"))]
+      .into_iter()
+      .collect();
+    let live: Vec<Workspace> = serde_json::from_str(
+      r#"[{"name": "1", "output": "eDP1"}, {"name": "2", "output": "HDMI-2"}]"#,
+    )
+    .unwrap();
+    let expected: Assignments = vec![(String::from("1"), String::from("A"))]
+      .into_iter()
+      .collect();
+    assert_eq!(expected, assignments(&live, &connectors));
+  }
+
+  #[test]
+  fn it_should_group_workspaces_by_output() {
+    let input = r#"[
+      {"name": "1", "focused": false, "output": "eDP1"},
+      {"name": "2: web", "focused": true, "output": "HDMI-2"},
+      {"name": "3", "focused": false, "output": "eDP1"}
+    ]"#;
+    let workspaces: Vec<Workspace> = serde_json::from_str(input).unwrap();
+    assert_eq!(
+      "HDMI-2\n  2: web (focused)\neDP1\n  1\n  3",
+      by_output(&workspaces)
+    );
+    assert_eq!("", by_output(&[]));
+  }
+
+  #[test]
+  fn it_should_quote_the_workspace_to_focus() {
+    assert_eq!(r#"workspace "2: web""#, focus_command("2: web"));
+    assert_eq!(r#"workspace "a\"b""#, focus_command("a\"b"));
+  }
+}